@@ -0,0 +1,120 @@
+//! 味方の艦隊のうち、どれを戦闘に参加させ、どれを休ませるかの最適化。
+//!
+//! 攻撃力は clamp 済みの総兵力を 100 単位に切り捨てて計算するので (`attack_force / 100 * coef`)、
+//! ブレークポイントを超える分の兵力は攻撃力に寄与しない。一方、休ませた (疲労フラグを立てた)
+//! 艦隊も艦隊数には数えられるので、1 艦隊あたりのダメージやフォーメーション修正には影響する。
+//! ここでは全艦隊を参加させた場合と同じ敵の損害を、できるだけ少ない艦隊で出す組み合わせを探す。
+
+use crate::battle::*;
+use crate::recommend::Outcome;
+
+/// 味方の参加させる艦隊と休ませる艦隊を選ぶ。
+///
+/// 兵力が 0 でなく疲労していない艦隊を「使える艦隊」とし、その空でない全ての部分集合を試す
+/// (全艦隊を休ませると総兵力 0 が clamp の下限まで引き上げられるので、候補にしない)。
+/// 使える艦隊を全て参加させた場合の敵の損害以上を出すもののうち、参加させる艦隊数が最小の
+/// ものを選ぶ。同数なら味方の損害が小さいもの、さらに同じなら若い番号の艦隊を参加させるものを選ぶ。
+///
+/// query が不正な場合、または使える艦隊がない場合は `None` を返す。
+pub fn optimize_ally_rest(query: &Query, rules: &RuleSet) -> Option<Allocation> {
+    let baseline = Outcome::new(query, rules)?;
+
+    let available: Vec<usize> = (0..ALLY_FLEET_COUNT)
+        .filter(|&i| !query.ally_fleet_force(i).is_zero() && !query.ally_fleet_is_tired(i))
+        .collect();
+    if available.is_empty() {
+        return None;
+    }
+
+    (1_u32..1 << available.len())
+        .filter_map(|mask| {
+            let is_committed = |bit: usize| mask & (1 << bit) != 0;
+            let committed: Vec<usize> = (0..available.len())
+                .filter(|&bit| is_committed(bit))
+                .map(|bit| available[bit])
+                .collect();
+            let rested: Vec<usize> = (0..available.len())
+                .filter(|&bit| !is_committed(bit))
+                .map(|bit| available[bit])
+                .collect();
+
+            let mut query = query.clone();
+            for &i in &rested {
+                query.set_ally_fleet_is_tired(i, true);
+            }
+
+            let outcome = Outcome::new(&query, rules)?;
+            (outcome.enemy_damage() >= baseline.enemy_damage()).then_some(Allocation {
+                query,
+                available: available.clone(),
+                committed,
+                rested,
+                outcome,
+                baseline_enemy_damage: baseline.enemy_damage(),
+                baseline_ally_loss: baseline.ally_loss(),
+                baseline_ally_formation: baseline.report().ally_formation(),
+            })
+        })
+        .min_by_key(|allocation| {
+            (
+                allocation.committed.len(),
+                allocation.outcome.ally_loss(),
+                allocation.committed.clone(),
+            )
+        })
+}
+
+/// `optimize_ally_rest()` の結果。
+#[derive(Debug)]
+pub struct Allocation {
+    query: Query,
+    available: Vec<usize>,
+    committed: Vec<usize>,
+    rested: Vec<usize>,
+    outcome: Outcome,
+    baseline_enemy_damage: u32,
+    baseline_ally_loss: u32,
+    baseline_ally_formation: Formation,
+}
+
+impl Allocation {
+    /// 休ませる艦隊の疲労フラグを立てた query を返す。
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// 使える艦隊 (兵力が 0 でなく、元から疲労していない艦隊) の番号 (0 始まり) を昇順に返す。
+    pub fn available_fleets(&self) -> &[usize] {
+        &self.available
+    }
+
+    /// 参加させる艦隊の番号 (0 始まり) を昇順に返す。
+    pub fn committed_fleets(&self) -> &[usize] {
+        &self.committed
+    }
+
+    /// 新たに休ませる艦隊の番号 (0 始まり) を昇順に返す。元から疲労している艦隊は含まない。
+    pub fn rested_fleets(&self) -> &[usize] {
+        &self.rested
+    }
+
+    /// この配分での戦闘結果を返す。
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+
+    /// 使える艦隊を全て参加させた場合の敵の損害を返す。
+    pub fn baseline_enemy_damage(&self) -> u32 {
+        self.baseline_enemy_damage
+    }
+
+    /// 使える艦隊を全て参加させた場合の味方の損害を返す。
+    pub fn baseline_ally_loss(&self) -> u32 {
+        self.baseline_ally_loss
+    }
+
+    /// 使える艦隊を全て参加させた場合の味方の修正後フォーメーションを返す。
+    pub fn baseline_ally_formation(&self) -> Formation {
+        self.baseline_ally_formation
+    }
+}
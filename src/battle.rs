@@ -1,37 +1,62 @@
-use anyhow::Context as _;
+use anyhow::{ensure, Context as _};
 use arrayvec::ArrayVec;
 
 use crate::util;
 
+mod ability;
+mod notation;
+mod rules;
+#[cfg(feature = "serde")]
+mod schema;
+mod table;
+mod trace;
+
+pub use self::ability::*;
+pub use self::rules::*;
+#[cfg(feature = "serde")]
+pub use self::schema::SCHEMA_VERSION;
+pub use self::table::*;
+pub use self::trace::*;
+
 pub const ALLY_FLEET_COUNT: usize = 11;
 pub const ENEMY_FLEET_COUNT: usize = 15;
 
+/// 1 陣営の艦隊数の上限 (敵の駐留艦隊を含む)。
+pub const FLEET_COUNT_MAX: usize = if ALLY_FLEET_COUNT > ENEMY_FLEET_COUNT + 1 {
+    ALLY_FLEET_COUNT
+} else {
+    ENEMY_FLEET_COUNT + 1
+};
+
 type AllyFleetForces = ArrayVec<FleetForce, ALLY_FLEET_COUNT>;
 type AllyFleetIsTireds = ArrayVec<bool, ALLY_FLEET_COUNT>;
 
 type EnemyFleetForces = ArrayVec<FleetForce, ENEMY_FLEET_COUNT>;
+type EnemyFleetIsTireds = ArrayVec<bool, ENEMY_FLEET_COUNT>;
 
-const ATTACK_FORCE_MIN: u32 = 100;
-const ATTACK_FORCE_MAX: u32 = 1600;
+const DAMAGE_PER_FLEET_MAX: u32 = 100;
 
-pub fn battle_simulate(query: &Query) -> Option<Report> {
+pub fn battle_simulate(query: &Query, rules: &RuleSet) -> Option<Report> {
     if !query.is_valid() {
         return None;
     }
 
-    let ally_formation = query.ally.formation_modified();
-    let enemy_formation = query.enemy.formation_modified();
+    let ally_formation = query.ally.formation_modified(rules);
+    let enemy_formation = query.enemy.formation_modified(rules);
 
-    let (ally_attack, enemy_attack) = calc_attacks(query);
+    let (ally_attack, enemy_attack) = calc_attacks(query, rules);
 
-    let ally_damage_per_fleet = calc_damage_per_fleet(enemy_attack, query.ally.fleet_count());
-    let enemy_damage_per_fleet = calc_damage_per_fleet(ally_attack, query.enemy.fleet_count());
+    let ally_damage_per_fleet =
+        calc_damage_per_fleet(enemy_attack, query.ally.fleet_count(), rules);
+    let enemy_damage_per_fleet =
+        calc_damage_per_fleet(ally_attack, query.enemy.fleet_count(), rules);
 
-    let ally_fleet_forces = damage_ally(&query.ally.fleet_forces, ally_damage_per_fleet);
+    let ally_fleet_forces = damage_ally(&query.ally.fleet_forces, ally_damage_per_fleet, rules);
     let (enemy_fleet_forces, enemy_guard_force) = damage_enemy(
         &query.enemy.fleet_forces,
         query.enemy.guard_force,
         enemy_damage_per_fleet,
+        rules,
     );
 
     Some(Report {
@@ -49,48 +74,185 @@ pub fn battle_simulate(query: &Query) -> Option<Report> {
     })
 }
 
-/// 味方と敵の攻撃力(ヤン補正済み)を求める。
-fn calc_attacks(query: &Query) -> (u32, u32) {
-    let ally_attack_force = query.ally.attack_force_clamped();
-    let ally_formation = query.ally.formation_modified();
+/// いずれかの陣営が全滅するか、ラウンド数が round_max に達するか、stop が true を返すまで
+/// 戦闘を繰り返す。
+///
+/// 各ラウンドの結果は次ラウンドの入力として使われる (フォーメーション修正もラウンドごとに再適用される)。
+pub fn battle_simulate_rounds<F>(
+    query: &Query,
+    rules: &RuleSet,
+    round_max: usize,
+    mut stop: F,
+) -> Option<Rounds>
+where
+    F: FnMut(&Report) -> bool,
+{
+    if !query.is_valid() {
+        return None;
+    }
 
-    let enemy_attack_force = query.enemy.attack_force_clamped();
-    let enemy_formation = query.enemy.formation_modified();
+    let mut query = query.clone();
+    let (rounds, end) = battle_simulate_rounds_with(
+        &mut query,
+        rules,
+        round_max,
+        |_| {},
+        |_, report| stop(report),
+    );
+    let reports = rounds.into_iter().map(|(_, report)| report).collect();
 
-    let mut ally_attack = calc_attack_raw(ally_attack_force, ally_formation, enemy_formation);
-    let mut enemy_attack = calc_attack_raw(enemy_attack_force, enemy_formation, ally_formation);
+    Some(Rounds { reports, end })
+}
 
-    if query.enemy.has_yang && query.ally.formation != FORMATION_5 {
-        ally_attack = ally_attack / 2 + 1;
-        enemy_attack += 10;
-    }
+/// `battle_simulate_rounds()` の本体。query は正当でなければならない。
+///
+/// 各ラウンドの前に prepare で query を変更できる (prepare は実際に行うラウンドの前にだけ呼ばれる)。
+/// 各ラウンドの (prepare 適用後の) 入力と結果の組を返し、query は最終ラウンド後の状態に更新する。
+pub(crate) fn battle_simulate_rounds_with<P, F>(
+    query: &mut Query,
+    rules: &RuleSet,
+    round_max: usize,
+    mut prepare: P,
+    mut stop: F,
+) -> (Vec<(Query, Report)>, RoundsEnd)
+where
+    P: FnMut(&mut Query),
+    F: FnMut(&Query, &Report) -> bool,
+{
+    let mut rounds = Vec::<(Query, Report)>::new();
+
+    let end = loop {
+        if rounds.len() >= round_max {
+            break RoundsEnd::RoundMax;
+        }
+
+        prepare(query);
+
+        let query_before = query.clone();
+        let report = battle_simulate(query, rules).expect("query should be valid");
+        query.apply_report(&report);
+
+        let end = match (report.ally_is_annihilated(), report.enemy_is_annihilated()) {
+            (true, true) => Some(RoundsEnd::BothAnnihilated),
+            (true, false) => Some(RoundsEnd::AllyAnnihilated),
+            (false, true) => Some(RoundsEnd::EnemyAnnihilated),
+            (false, false) => stop(&query_before, &report).then_some(RoundsEnd::Stopped),
+        };
+
+        rounds.push((query_before, report));
+
+        if let Some(end) = end {
+            break end;
+        }
+    };
+
+    (rounds, end)
+}
+
+/// 味方と敵の攻撃力(能力補正済み)を求める。
+fn calc_attacks(query: &Query, rules: &RuleSet) -> (u32, u32) {
+    let ally_attack_force = query.ally.attack_force_clamped(rules);
+    let ally_formation = query.ally.formation_modified(rules);
+
+    let enemy_attack_force = query.enemy.attack_force_clamped(rules);
+    let enemy_formation = query.enemy.formation_modified(rules);
+
+    let ally_attack = calc_attack_raw(ally_attack_force, ally_formation, enemy_formation, rules);
+    let enemy_attack = calc_attack_raw(enemy_attack_force, enemy_formation, ally_formation, rules);
+
+    adjust_attacks(
+        ally_attack,
+        enemy_attack,
+        &query.ally_abilities_effective(rules),
+        &query.enemy_abilities_effective(rules),
+    )
+}
+
+/// 味方と敵の攻撃力に能力補正を適用して返す。
+///
+/// 両陣営の攻撃力への加算を先に行い、その後で相手陣営の攻撃力への補正を行う。
+/// 同じ陣営に複数の能力がある場合は順に適用する。
+fn adjust_attacks(
+    ally_attack: u32,
+    enemy_attack: u32,
+    ally_abilities: &[Ability],
+    enemy_abilities: &[Ability],
+) -> (u32, u32) {
+    let ally_attack = ally_abilities.iter().fold(ally_attack, |attack, ability| {
+        ability.adjust_attack_us(attack)
+    });
+    let enemy_attack = enemy_abilities
+        .iter()
+        .fold(enemy_attack, |attack, ability| {
+            ability.adjust_attack_us(attack)
+        });
+
+    let ally_attack = enemy_abilities.iter().fold(ally_attack, |attack, ability| {
+        ability.adjust_attack_them(attack)
+    });
+    let enemy_attack = ally_abilities.iter().fold(enemy_attack, |attack, ability| {
+        ability.adjust_attack_them(attack)
+    });
 
     (ally_attack, enemy_attack)
 }
 
-/// ヤン補正前の攻撃力を求める。
-fn calc_attack_raw(attack_force: u32, formation_us: Formation, formation_them: Formation) -> u32 {
-    let coef = formation_us.attack_coef(formation_them);
+/// 能力のうち、相手陣営の (修正前の) フォーメーションが formation_them のとき有効なものを返す。
+///
+/// `Ability::YANG` は rules のヤンの補正に置き換えてから判定する。
+fn abilities_effective(
+    abilities: &[Ability],
+    formation_them: Formation,
+    rules: &RuleSet,
+) -> Vec<Ability> {
+    abilities
+        .iter()
+        .map(|&ability| {
+            if ability == Ability::YANG {
+                rules.yang()
+            } else {
+                ability
+            }
+        })
+        .filter(|ability| !ability.is_exempt(formation_them))
+        .collect()
+}
 
-    attack_force / 100 * coef
+/// 能力補正前の攻撃力を求める。
+fn calc_attack_raw(
+    attack_force: u32,
+    formation_us: Formation,
+    formation_them: Formation,
+    rules: &RuleSet,
+) -> u32 {
+    let coef = rules.attack_coef(formation_us, formation_them);
+
+    (attack_force / 100).saturating_mul(coef)
 }
 
 /// 1 艦隊あたりのダメージを求める。
-fn calc_damage_per_fleet(attack_them: u32, fleet_count_us: usize) -> u32 {
-    let fleet_count_us = util::u32_from_usize(fleet_count_us);
+fn calc_damage_per_fleet(attack_them: u32, fleet_count_us: usize, rules: &RuleSet) -> u32 {
+    calc_damage_per_fleet_uncapped(attack_them, fleet_count_us, rules).min(DAMAGE_PER_FLEET_MAX)
+}
 
-    (100 * attack_them / (12 * fleet_count_us)).min(100)
+/// 1 艦隊あたりのダメージを上限を適用せずに求める。
+fn calc_damage_per_fleet_uncapped(attack_them: u32, fleet_count_us: usize, rules: &RuleSet) -> u32 {
+    let numer = 100 * u64::from(attack_them);
+    let denom = u64::from(rules.damage_divisor()) * util::u64_from_usize(fleet_count_us);
+
+    // 結果が u32 に収まらない場合は飽和させる。
+    u32::try_from(numer / denom).unwrap_or(u32::MAX)
 }
 
 /// 味方陣営にダメージを与えた結果を返す。
-fn damage_ally(fleet_forces: &AllyFleetForces, damage_per_fleet: u32) -> AllyFleetForces {
+fn damage_ally(
+    fleet_forces: &AllyFleetForces,
+    damage_per_fleet: u32,
+    rules: &RuleSet,
+) -> AllyFleetForces {
     fleet_forces
         .iter()
-        .map(|fleet_force| {
-            let x = fleet_force.inner().saturating_sub(damage_per_fleet);
-            let x = if x <= 8 { 0 } else { x };
-            FleetForce::new(x).expect("damaged ally fleet force should be valid")
-        })
+        .map(|&fleet_force| damage_fleet(fleet_force, damage_per_fleet, rules.annihilation_fleet()))
         .collect()
 }
 
@@ -99,26 +261,28 @@ fn damage_enemy(
     fleet_forces: &EnemyFleetForces,
     guard_force: FleetForce,
     damage_per_fleet: u32,
+    rules: &RuleSet,
 ) -> (EnemyFleetForces, FleetForce) {
     let fleet_forces: EnemyFleetForces = fleet_forces
         .iter()
-        .map(|fleet_force| {
-            let x = fleet_force.inner().saturating_sub(damage_per_fleet);
-            let x = if x <= 8 { 0 } else { x };
-            FleetForce::new(x).expect("damaged enemy fleet force should be valid")
-        })
+        .map(|&fleet_force| damage_fleet(fleet_force, damage_per_fleet, rules.annihilation_fleet()))
         .collect();
 
-    let guard_force = {
-        let x = guard_force.inner().saturating_sub(damage_per_fleet);
-        let x = if x < 8 { 0 } else { x };
-        FleetForce::new(x).expect("damaged enemy guard force should be valid")
-    };
+    let guard_force = damage_fleet(guard_force, damage_per_fleet, rules.annihilation_guard());
 
     (fleet_forces, guard_force)
 }
 
-#[derive(Debug, Default)]
+/// 1 個艦隊にダメージを与えた結果を返す。
+///
+/// ダメージ適用後の兵力が annihilation 以下なら全滅する。
+fn damage_fleet(fleet_force: FleetForce, damage_per_fleet: u32, annihilation: u32) -> FleetForce {
+    let x = fleet_force.inner().saturating_sub(damage_per_fleet);
+    let x = if x <= annihilation { 0 } else { x };
+    FleetForce::new(x).expect("damaged fleet force should be valid")
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Query {
     ally: QueryAlly,
     enemy: QueryEnemy,
@@ -157,6 +321,14 @@ impl Query {
         self.enemy.fleet_forces[idx] = fleet_force;
     }
 
+    pub fn enemy_fleet_is_tired(&self, idx: usize) -> bool {
+        self.enemy.fleet_is_tireds[idx]
+    }
+
+    pub fn set_enemy_fleet_is_tired(&mut self, idx: usize, is_tired: bool) {
+        self.enemy.fleet_is_tireds[idx] = is_tired;
+    }
+
     pub fn enemy_guard_force(&self) -> FleetForce {
         self.enemy.guard_force
     }
@@ -165,6 +337,14 @@ impl Query {
         self.enemy.guard_force = fleet_force;
     }
 
+    pub fn enemy_guard_is_tired(&self) -> bool {
+        self.enemy.guard_is_tired
+    }
+
+    pub fn set_enemy_guard_is_tired(&mut self, is_tired: bool) {
+        self.enemy.guard_is_tired = is_tired;
+    }
+
     pub fn enemy_formation(&self) -> Formation {
         self.enemy.formation
     }
@@ -173,24 +353,96 @@ impl Query {
         self.enemy.formation = formation;
     }
 
+    /// 味方の指揮官の能力を返す。
+    pub fn ally_abilities(&self) -> &[Ability] {
+        &self.ally.abilities
+    }
+
+    pub fn set_ally_abilities(&mut self, abilities: Vec<Ability>) {
+        self.ally.abilities = abilities;
+    }
+
+    /// 敵の指揮官の能力を返す。
+    pub fn enemy_abilities(&self) -> &[Ability] {
+        &self.enemy.abilities
+    }
+
+    pub fn set_enemy_abilities(&mut self, abilities: Vec<Ability>) {
+        self.enemy.abilities = abilities;
+    }
+
+    /// 敵にヤンがいるかどうかを返す。
     pub fn enemy_has_yang(&self) -> bool {
-        self.enemy.has_yang
+        self.enemy.abilities.contains(&Ability::YANG)
     }
 
+    /// 敵のヤンの有無を設定する。他の能力はそのまま。
     pub fn set_enemy_has_yang(&mut self, has_yang: bool) {
-        self.enemy.has_yang = has_yang;
+        self.enemy
+            .abilities
+            .retain(|&ability| ability != Ability::YANG);
+        if has_yang {
+            self.enemy.abilities.push(Ability::YANG);
+        }
+    }
+
+    /// 味方の能力のうち有効なもの (敵の修正前フォーメーションで判定) を返す。
+    fn ally_abilities_effective(&self, rules: &RuleSet) -> Vec<Ability> {
+        abilities_effective(&self.ally.abilities, self.enemy.formation, rules)
+    }
+
+    /// 敵の能力のうち有効なもの (味方の修正前フォーメーションで判定) を返す。
+    fn enemy_abilities_effective(&self, rules: &RuleSet) -> Vec<Ability> {
+        abilities_effective(&self.enemy.abilities, self.ally.formation, rules)
+    }
+
+    /// 味方の総兵力 (疲労中の艦隊含む) を返す。
+    pub fn ally_force_total(&self) -> u32 {
+        force_total(&self.ally.fleet_forces)
+    }
+
+    /// 敵の総兵力 (駐留艦隊・疲労中の艦隊含む) を返す。
+    pub fn enemy_force_total(&self) -> u32 {
+        force_total(&self.enemy.fleet_forces) + self.enemy.guard_force.inner()
+    }
+
+    /// 敵の健在な艦隊数 (駐留艦隊含む) を返す。
+    pub fn enemy_fleet_count(&self) -> usize {
+        self.enemy.fleet_count()
     }
 
     pub fn is_valid(&self) -> bool {
-        self.ally.is_valid() && self.enemy.is_valid()
+        self.validate().is_ok()
+    }
+
+    /// 戦闘を計算できる入力かどうかを検査し、できなければ理由をエラーとして返す。
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.ally.is_valid(),
+            "ally needs at least one fleet with nonzero force"
+        );
+        ensure!(
+            self.enemy.is_valid(),
+            "enemy needs at least one fleet (or the guard) with nonzero force"
+        );
+
+        Ok(())
+    }
+
+    /// 戦闘結果の兵力を反映する。フォーメーションなどそれ以外の値はそのまま。
+    pub fn apply_report(&mut self, report: &Report) {
+        self.ally.fleet_forces = report.ally.fleet_forces.clone();
+        self.enemy.fleet_forces = report.enemy.fleet_forces.clone();
+        self.enemy.guard_force = report.enemy.guard_force;
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 struct QueryAlly {
     fleet_forces: AllyFleetForces,
     fleet_is_tireds: AllyFleetIsTireds,
     formation: Formation,
+    abilities: Vec<Ability>,
 }
 
 impl QueryAlly {
@@ -200,19 +452,25 @@ impl QueryAlly {
     }
 
     /// 攻撃可能な総兵力 (clamp 済み) を得る。
-    fn attack_force_clamped(&self) -> u32 {
-        clamp_attack_force(self.attack_force())
+    fn attack_force_clamped(&self, rules: &RuleSet) -> u32 {
+        clamp_attack_force(self.attack_force(), rules)
     }
 
     /// フォーメーション (修正済み) を得る。
-    fn formation_modified(&self) -> Formation {
-        modify_formation(self.fleet_count(), self.attack_force(), self.formation)
+    fn formation_modified(&self, rules: &RuleSet) -> Formation {
+        modify_formation(
+            self.fleet_count(),
+            self.attack_force(),
+            self.formation,
+            rules,
+        )
     }
 
     /// 攻撃可能な総兵力 (clamp なし) を得る。
     fn attack_force(&self) -> u32 {
         (0..ALLY_FLEET_COUNT)
-            .filter_map(|i| (!self.fleet_is_tireds[i]).then(|| self.fleet_forces[i].inner()))
+            .filter(|&i| !self.fleet_is_tireds[i])
+            .map(|i| self.fleet_forces[i].inner())
             .sum()
     }
 
@@ -232,47 +490,55 @@ impl Default for QueryAlly {
             fleet_forces,
             fleet_is_tireds,
             formation: FORMATION_1,
+            abilities: vec![],
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 struct QueryEnemy {
     fleet_forces: EnemyFleetForces,
+    fleet_is_tireds: EnemyFleetIsTireds,
     guard_force: FleetForce,
+    guard_is_tired: bool,
     formation: Formation,
-    has_yang: bool,
+    abilities: Vec<Ability>,
 }
 
 impl QueryEnemy {
     /// 健在な艦隊数(駐留艦隊含む)を得る。
     fn fleet_count(&self) -> usize {
-        let count_active = self.fleet_forces.iter().filter(|e| !e.is_zero()).count();
-        let count_guard = if self.guard_force.is_zero() { 0 } else { 1 };
-
-        count_active + count_guard
+        enemy_fleet_count(&self.fleet_forces, self.guard_force)
     }
 
     /// 攻撃可能な総兵力 (clamp 済み) を得る。
-    fn attack_force_clamped(&self) -> u32 {
-        clamp_attack_force(self.attack_force())
+    fn attack_force_clamped(&self, rules: &RuleSet) -> u32 {
+        clamp_attack_force(self.attack_force(), rules)
     }
 
     /// フォーメーション (修正済み) を得る。
-    fn formation_modified(&self) -> Formation {
-        modify_formation(self.fleet_count(), self.attack_force(), self.formation)
+    fn formation_modified(&self, rules: &RuleSet) -> Formation {
+        modify_formation(
+            self.fleet_count(),
+            self.attack_force(),
+            self.formation,
+            rules,
+        )
     }
 
     /// 攻撃可能な総兵力 (clamp なし) を得る。
     fn attack_force(&self) -> u32 {
-        let force_active: u32 = self
-            .fleet_forces
-            .iter()
-            .copied()
-            .map(FleetForce::inner)
+        let force_active: u32 = (0..ENEMY_FLEET_COUNT)
+            .filter(|&i| !self.fleet_is_tireds[i])
+            .map(|i| self.fleet_forces[i].inner())
             .sum();
+        let force_guard = if self.guard_is_tired {
+            0
+        } else {
+            self.guard_force.inner()
+        };
 
-        force_active + self.guard_force.inner()
+        force_active + force_guard
     }
 
     fn is_valid(&self) -> bool {
@@ -284,34 +550,70 @@ impl Default for QueryEnemy {
     fn default() -> Self {
         let fleet_forces = EnemyFleetForces::from([FleetForce::zero(); ENEMY_FLEET_COUNT]);
 
+        let fleet_is_tireds = EnemyFleetIsTireds::from([false; ENEMY_FLEET_COUNT]);
+
         let guard_force = FleetForce::MAX;
 
         Self {
             fleet_forces,
+            fleet_is_tireds,
             guard_force,
+            guard_is_tired: false,
             formation: FORMATION_1,
-            has_yang: false,
+            abilities: vec![],
         }
     }
 }
 
+/// 艦隊の兵力の合計を返す。
+fn force_total(fleet_forces: &[FleetForce]) -> u32 {
+    fleet_forces.iter().copied().map(FleetForce::inner).sum()
+}
+
+/// 敵の健在な艦隊数 (駐留艦隊含む) を返す。
+fn enemy_fleet_count(fleet_forces: &[FleetForce], guard_force: FleetForce) -> usize {
+    let count_active = fleet_forces.iter().filter(|e| !e.is_zero()).count();
+    let count_guard = if guard_force.is_zero() { 0 } else { 1 };
+
+    count_active + count_guard
+}
+
 /// 攻撃可能な総兵力を clamp して返す。
-fn clamp_attack_force(attack_force: u32) -> u32 {
-    num_traits::clamp(attack_force, ATTACK_FORCE_MIN, ATTACK_FORCE_MAX)
+fn clamp_attack_force(attack_force: u32, rules: &RuleSet) -> u32 {
+    num_traits::clamp(
+        attack_force,
+        rules.attack_force_min(),
+        rules.attack_force_max(),
+    )
 }
 
 /// 損害率によりフォーメーションを修正して返す。
-fn modify_formation(fleet_count: usize, attack_force: u32, formation: Formation) -> Formation {
-    let numer = attack_force.min(ATTACK_FORCE_MAX);
-    let denom = util::u32_from_usize(10 * fleet_count);
-
-    if numer / denom <= 3 {
+fn modify_formation(
+    fleet_count: usize,
+    attack_force: u32,
+    formation: Formation,
+    rules: &RuleSet,
+) -> Formation {
+    if formation_is_forced(fleet_count, attack_force, rules) {
         FORMATION_0
     } else {
         formation
     }
 }
 
+/// フォーメーション修正によりフォーメーション 0 が強制されるかどうかを返す。
+fn formation_is_forced(fleet_count: usize, attack_force: u32, rules: &RuleSet) -> bool {
+    formation_ratio(fleet_count, attack_force, rules) <= rules.formation_ratio_forced()
+}
+
+/// フォーメーション修正の判定に使う値 (艦隊あたりの兵力を 10 で割ったもの) を返す。
+fn formation_ratio(fleet_count: usize, attack_force: u32, rules: &RuleSet) -> u32 {
+    let numer = attack_force.min(rules.attack_force_max());
+    let denom = util::u32_from_usize(10 * fleet_count);
+
+    numer / denom
+}
+
 #[derive(Debug)]
 pub struct Report {
     ally: ReportAlly,
@@ -346,6 +648,31 @@ impl Report {
     pub fn enemy_guard_force(&self) -> FleetForce {
         self.enemy.guard_force
     }
+
+    /// 味方が全滅したかどうかを返す。
+    pub fn ally_is_annihilated(&self) -> bool {
+        self.ally.fleet_forces.iter().all(|e| e.is_zero())
+    }
+
+    /// 敵が全滅したかどうかを返す (駐留艦隊含む)。
+    pub fn enemy_is_annihilated(&self) -> bool {
+        self.enemy.guard_force.is_zero() && self.enemy.fleet_forces.iter().all(|e| e.is_zero())
+    }
+
+    /// 味方の総兵力を返す。
+    pub fn ally_force_total(&self) -> u32 {
+        force_total(&self.ally.fleet_forces)
+    }
+
+    /// 敵の総兵力 (駐留艦隊含む) を返す。
+    pub fn enemy_force_total(&self) -> u32 {
+        force_total(&self.enemy.fleet_forces) + self.enemy.guard_force.inner()
+    }
+
+    /// 敵の健在な艦隊数 (駐留艦隊含む) を返す。
+    pub fn enemy_fleet_count(&self) -> usize {
+        enemy_fleet_count(&self.enemy.fleet_forces, self.enemy.guard_force)
+    }
 }
 
 #[derive(Debug)]
@@ -363,8 +690,42 @@ struct ReportEnemy {
     guard_force: FleetForce,
 }
 
+/// 複数ラウンドの戦闘結果。
+#[derive(Debug)]
+pub struct Rounds {
+    reports: Vec<Report>,
+    end: RoundsEnd,
+}
+
+impl Rounds {
+    /// 各ラウンドの結果を返す。
+    pub fn reports(&self) -> &[Report] {
+        &self.reports
+    }
+
+    /// 戦闘が終了した理由を返す。
+    pub fn end(&self) -> RoundsEnd {
+        self.end
+    }
+}
+
+/// 複数ラウンドの戦闘が終了した理由。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RoundsEnd {
+    AllyAnnihilated,
+    EnemyAnnihilated,
+    BothAnnihilated,
+    RoundMax,
+    Stopped,
+}
+
 /// 1 個艦隊内の兵力。
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u32", into = "u32")
+)]
 pub struct FleetForce(u32);
 
 impl FleetForce {
@@ -374,7 +735,7 @@ impl FleetForce {
     pub fn new(inner: u32) -> Option<Self> {
         (Self::MIN.0..=Self::MAX.0)
             .contains(&inner)
-            .then(|| Self(inner))
+            .then_some(Self(inner))
     }
 
     pub const fn zero() -> Self {
@@ -390,13 +751,27 @@ impl FleetForce {
     }
 }
 
+impl TryFrom<u32> for FleetForce {
+    type Error = anyhow::Error;
+
+    fn try_from(inner: u32) -> Result<Self, Self::Error> {
+        Self::new(inner).with_context(|| format!("force value is out of range: {inner}"))
+    }
+}
+
+impl From<FleetForce> for u32 {
+    fn from(fleet_force: FleetForce) -> Self {
+        fleet_force.0
+    }
+}
+
 impl std::str::FromStr for FleetForce {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let inner: u32 = s.parse()?;
 
-        Self::new(inner).context("force value is out of range")
+        Self::try_from(inner)
     }
 }
 
@@ -407,38 +782,91 @@ impl std::fmt::Display for FleetForce {
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
 pub struct Formation(u8);
 
 const FORMATION_0: Formation = Formation(0);
 const FORMATION_1: Formation = Formation(1);
 const FORMATION_5: Formation = Formation(5);
 
+/// 各フォーメーションの (日本語, 英語) の表示名。
+const FORMATION_NAMES: [Option<(&str, &str)>; Formation::COUNT] = [
+    Some(("崩壊", "Broken")),
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+];
+
 impl Formation {
     pub const MIN: Self = Self(0);
     pub const MAX: Self = Self(7);
 
+    /// フォーメーションの種類数。
+    pub const COUNT: usize = (Self::MAX.0 - Self::MIN.0 + 1) as usize;
+
     pub fn new(inner: u8) -> Option<Self> {
         (Self::MIN.0..=Self::MAX.0)
             .contains(&inner)
-            .then(|| Self(inner))
+            .then_some(Self(inner))
     }
 
-    /// 自陣営フォーメーション self, 相手陣営フォーメーション them のときのフォーメーション係数を返す。
-    fn attack_coef(self, them: Self) -> u32 {
-        const COUNT: usize = (Formation::MAX.0 - Formation::MIN.0 + 1) as usize;
+    /// 全フォーメーションを昇順に列挙する。
+    pub fn all() -> impl Iterator<Item = Self> {
+        (Self::MIN.0..=Self::MAX.0).map(Self)
+    }
 
-        const TABLE: [[u32; COUNT]; COUNT] = [
-            [3, 1, 1, 1, 1, 1, 1, 1],
-            [5, 4, 3, 5, 4, 2, 2, 4],
-            [5, 3, 2, 3, 4, 2, 1, 3],
-            [5, 3, 3, 4, 4, 4, 3, 5],
-            [5, 4, 2, 4, 5, 4, 5, 5],
-            [5, 4, 3, 3, 3, 3, 2, 2],
-            [5, 2, 3, 3, 3, 1, 1, 3],
-            [5, 4, 4, 2, 5, 5, 3, 5],
-        ];
+    /// `Formation::all()` における位置を返す。
+    pub fn index(self) -> usize {
+        usize::from(self.0 - Self::MIN.0)
+    }
+
+    /// 1 艦隊あたりの兵力が少ないときフォーメーション修正により強制される、崩れたフォーメーション
+    /// かどうかを返す。
+    pub fn is_broken(self) -> bool {
+        self == FORMATION_0
+    }
+
+    /// 日本語の表示名を返す。名前がなければ `None` (表示側は番号を使う)。
+    ///
+    /// フォーメーション 0 の名前はこのクレートでの呼び名。フォーメーション 1..=7 はゲーム内の名前を
+    /// 実機で確認できたら `FORMATION_NAMES` に加える。
+    pub fn name_ja(self) -> Option<&'static str> {
+        FORMATION_NAMES[self.index()].map(|(ja, _)| ja)
+    }
+
+    /// 英語の表示名を返す。名前がなければ `None`。
+    pub fn name_en(self) -> Option<&'static str> {
+        FORMATION_NAMES[self.index()].map(|(_, en)| en)
+    }
+
+    /// 規則によらない短い説明を返す。特記事項がなければ `None`。
+    pub fn description(self) -> Option<&'static str> {
+        match self.0 {
+            0 => Some("1 艦隊あたりの兵力が少ないとき強制される。係数が極端に低い"),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u8> for Formation {
+    type Error = anyhow::Error;
+
+    fn try_from(inner: u8) -> Result<Self, Self::Error> {
+        Self::new(inner).with_context(|| format!("formation value is out of range: {inner}"))
+    }
+}
 
-        TABLE[usize::from(self.0)][usize::from(them.0)]
+impl From<Formation> for u8 {
+    fn from(formation: Formation) -> Self {
+        formation.0
     }
 }
 
@@ -448,7 +876,7 @@ impl std::str::FromStr for Formation {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let inner: u8 = s.parse()?;
 
-        Self::new(inner).context("formation value is out of range")
+        Self::try_from(inner)
     }
 }
 
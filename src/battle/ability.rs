@@ -0,0 +1,159 @@
+//! 指揮官の能力。
+//!
+//! 簡易記法などでの文字列表現は `<attack_bonus>:<defense_divisor>:<defense_offset>[:<exempt>]`。
+//! exempt は無効になる相手フォーメーションを並べたもの (例: `05`)。ヤンは `yang` とも書ける。
+//! attack_bonus と defense_offset は `ABILITY_VALUE_MAX` 以下。
+
+use anyhow::{ensure, Context as _};
+
+use super::*;
+
+/// 能力の `attack_bonus` と `defense_offset` の上限。
+pub const ABILITY_VALUE_MAX: u32 = 1000;
+
+/// 指揮官の能力による攻撃力の補正。
+///
+/// 能力を持つ陣営の攻撃力に `attack_bonus` を足し、相手陣営の攻撃力を `defense_divisor` で
+/// 割って `defense_offset` を足す。相手陣営の (修正前の) フォーメーションが `exempt` に
+/// 含まれる場合は適用されない。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Ability {
+    attack_bonus: u32,
+    defense_divisor: u32,
+    defense_offset: u32,
+    exempt: [bool; Formation::COUNT],
+}
+
+impl Ability {
+    /// ヤン。相手陣営のフォーメーションが 5 なら無効。
+    pub const YANG: Self = Self {
+        attack_bonus: 10,
+        defense_divisor: 2,
+        defense_offset: 1,
+        exempt: {
+            let mut exempt = [false; Formation::COUNT];
+            exempt[FORMATION_5.0 as usize] = true;
+            exempt
+        },
+    };
+
+    /// 無効になるフォーメーションのない能力を作る。
+    ///
+    /// defense_divisor が 0 の場合、または attack_bonus か defense_offset が `ABILITY_VALUE_MAX`
+    /// を超える場合は `None` を返す。
+    pub fn new(attack_bonus: u32, defense_divisor: u32, defense_offset: u32) -> Option<Self> {
+        let is_valid = defense_divisor > 0
+            && attack_bonus <= ABILITY_VALUE_MAX
+            && defense_offset <= ABILITY_VALUE_MAX;
+
+        is_valid.then_some(Self {
+            attack_bonus,
+            defense_divisor,
+            defense_offset,
+            exempt: [false; Formation::COUNT],
+        })
+    }
+
+    /// 相手陣営のフォーメーションが formation のとき無効になるようにしたものを返す。
+    pub fn with_exempt(mut self, formation: Formation) -> Self {
+        self.exempt[formation.index()] = true;
+        self
+    }
+
+    pub fn attack_bonus(&self) -> u32 {
+        self.attack_bonus
+    }
+
+    pub fn defense_divisor(&self) -> u32 {
+        self.defense_divisor
+    }
+
+    pub fn defense_offset(&self) -> u32 {
+        self.defense_offset
+    }
+
+    /// 相手陣営のフォーメーションが formation のとき無効かどうかを返す。
+    pub fn is_exempt(&self, formation: Formation) -> bool {
+        self.exempt[formation.index()]
+    }
+
+    /// 能力を持つ陣営の攻撃力を補正して返す。
+    pub(super) fn adjust_attack_us(&self, attack: u32) -> u32 {
+        attack.saturating_add(self.attack_bonus)
+    }
+
+    /// 相手陣営の攻撃力を補正して返す。
+    pub(super) fn adjust_attack_them(&self, attack: u32) -> u32 {
+        (attack / self.defense_divisor).saturating_add(self.defense_offset)
+    }
+}
+
+impl std::str::FromStr for Ability {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "yang" {
+            return Ok(Self::YANG);
+        }
+
+        let fields: Vec<_> = s.split(':').collect();
+        ensure!(
+            (3..=4).contains(&fields.len()),
+            "ability should be `yang` or `<attack_bonus>:<defense_divisor>:<defense_offset>[:<exempt>]`: {s}"
+        );
+
+        let attack_bonus = fields[0]
+            .parse()
+            .with_context(|| format!("invalid attack bonus: {}", fields[0]))?;
+        let defense_divisor = fields[1]
+            .parse()
+            .with_context(|| format!("invalid defense divisor: {}", fields[1]))?;
+        let defense_offset = fields[2]
+            .parse()
+            .with_context(|| format!("invalid defense offset: {}", fields[2]))?;
+
+        ensure!(
+            attack_bonus <= ABILITY_VALUE_MAX,
+            "attack bonus should be at most {ABILITY_VALUE_MAX}: {attack_bonus}"
+        );
+        ensure!(
+            defense_offset <= ABILITY_VALUE_MAX,
+            "defense offset should be at most {ABILITY_VALUE_MAX}: {defense_offset}"
+        );
+        let mut ability = Self::new(attack_bonus, defense_divisor, defense_offset)
+            .context("defense divisor should be nonzero")?;
+
+        if let Some(exempt) = fields.get(3) {
+            for c in exempt.chars() {
+                let formation: Formation = c.to_string().parse()?;
+                ability = ability.with_exempt(formation);
+            }
+        }
+
+        Ok(ability)
+    }
+}
+
+impl std::fmt::Display for Ability {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if *self == Self::YANG {
+            return write!(f, "yang");
+        }
+
+        write!(
+            f,
+            "{}:{}:{}",
+            self.attack_bonus, self.defense_divisor, self.defense_offset
+        )?;
+
+        let exempt: String = Formation::all()
+            .filter(|&formation| self.is_exempt(formation))
+            .map(|formation| formation.to_string())
+            .collect();
+        if !exempt.is_empty() {
+            write!(f, ":{exempt}")?;
+        }
+
+        Ok(())
+    }
+}
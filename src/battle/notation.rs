@@ -0,0 +1,241 @@
+//! `Query` の簡易記法。
+//!
+//! ```text
+//! ally=100,100,80 f=3 tired=2; enemy=guard:100,50,50 f=1 tired=guard,1 yang
+//! ```
+//!
+//! * `ally=` / `enemy=` の後に艦隊の兵力を艦隊番号順にカンマ区切りで書く。省略した艦隊の兵力は 0。
+//! * 敵の駐留艦隊の兵力は先頭に `guard:` を付けて書く。省略すると 0。
+//! * `f=` はフォーメーション (省略時 1)。
+//! * `tired=` は疲労度 80 以上の艦隊の番号 (1 始まり) をカンマ区切りで書く。敵の駐留艦隊は `guard`。
+//! * `yang` はヤン参戦。`ability=` で任意の指揮官の能力を書ける (書式は `Ability` を参照)。
+//!   どちらも味方・敵の両方に書ける。
+//! * 味方と敵は `;` で区切る。順序は問わないが、両方必要。
+
+use anyhow::{bail, ensure, Context as _};
+
+use super::*;
+
+impl std::str::FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut query = Query::default();
+        let mut has_ally = false;
+        let mut has_enemy = false;
+
+        for section in s.split(';') {
+            let mut tokens = section.split_whitespace();
+            let Some(head) = tokens.next() else {
+                continue;
+            };
+
+            if let Some(forces) = head.strip_prefix("ally=") {
+                ensure!(!has_ally, "ally section appears twice");
+                has_ally = true;
+                query.ally = parse_ally(forces, tokens).context("invalid ally section")?;
+            } else if let Some(forces) = head.strip_prefix("enemy=") {
+                ensure!(!has_enemy, "enemy section appears twice");
+                has_enemy = true;
+                query.enemy = parse_enemy(forces, tokens).context("invalid enemy section")?;
+            } else {
+                bail!("section should start with `ally=` or `enemy=`: {head}");
+            }
+        }
+
+        ensure!(has_ally, "ally section is missing");
+        ensure!(has_enemy, "enemy section is missing");
+
+        Ok(query)
+    }
+}
+
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "ally={} f={}",
+            join_forces(&self.ally.fleet_forces),
+            self.ally.formation
+        )?;
+
+        write_tireds(f, &self.ally.fleet_is_tireds, false)?;
+        write_abilities(f, &self.ally.abilities)?;
+
+        write!(f, "; enemy=")?;
+        if !self.enemy.guard_force.is_zero() {
+            write!(f, "guard:{},", self.enemy.guard_force)?;
+        }
+        write!(
+            f,
+            "{} f={}",
+            join_forces(&self.enemy.fleet_forces),
+            self.enemy.formation
+        )?;
+
+        write_tireds(f, &self.enemy.fleet_is_tireds, self.enemy.guard_is_tired)?;
+
+        write_abilities(f, &self.enemy.abilities)?;
+
+        Ok(())
+    }
+}
+
+fn parse_ally<'a>(
+    forces: &str,
+    options: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<QueryAlly> {
+    let mut ally = QueryAlly {
+        fleet_forces: parse_forces(forces)?,
+        ..QueryAlly::default()
+    };
+
+    for option in options {
+        if let Some(s) = option.strip_prefix("f=") {
+            ally.formation = s.parse()?;
+        } else if let Some(s) = option.strip_prefix("tired=") {
+            for x in s.split(',') {
+                let i = parse_fleet_number(x, ALLY_FLEET_COUNT)?;
+                ally.fleet_is_tireds[i] = true;
+            }
+        } else if let Some(ability) = parse_ability(option)? {
+            ally.abilities.push(ability);
+        } else {
+            bail!("unknown ally option: {option}");
+        }
+    }
+
+    Ok(ally)
+}
+
+fn parse_enemy<'a>(
+    forces: &str,
+    options: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<QueryEnemy> {
+    let (guard_force, forces) = match forces.strip_prefix("guard:") {
+        Some(rest) => {
+            let (guard, rest) = rest.split_once(',').unwrap_or((rest, ""));
+            (guard.parse()?, rest)
+        }
+        None => (FleetForce::zero(), forces),
+    };
+
+    let mut enemy = QueryEnemy {
+        fleet_forces: parse_forces(forces)?,
+        guard_force,
+        ..QueryEnemy::default()
+    };
+
+    for option in options {
+        if let Some(s) = option.strip_prefix("f=") {
+            enemy.formation = s.parse()?;
+        } else if let Some(s) = option.strip_prefix("tired=") {
+            for x in s.split(',') {
+                if x == "guard" {
+                    enemy.guard_is_tired = true;
+                } else {
+                    let i = parse_fleet_number(x, ENEMY_FLEET_COUNT)?;
+                    enemy.fleet_is_tireds[i] = true;
+                }
+            }
+        } else if let Some(ability) = parse_ability(option)? {
+            enemy.abilities.push(ability);
+        } else {
+            bail!("unknown enemy option: {option}");
+        }
+    }
+
+    Ok(enemy)
+}
+
+/// `yang` または `ability=...` を読み取る。どちらでもなければ `None` を返す。
+fn parse_ability(option: &str) -> anyhow::Result<Option<Ability>> {
+    if option == "yang" {
+        return Ok(Some(Ability::YANG));
+    }
+
+    option
+        .strip_prefix("ability=")
+        .map(|s| s.parse().context("invalid ability"))
+        .transpose()
+}
+
+/// 1 始まりの艦隊番号を読み取り、0 始まりのインデックスを返す。
+fn parse_fleet_number(s: &str, count: usize) -> anyhow::Result<usize> {
+    let i: usize = s
+        .parse()
+        .with_context(|| format!("invalid fleet number: {s}"))?;
+    ensure!(
+        (1..=count).contains(&i),
+        "fleet number is out of range: {i}"
+    );
+
+    Ok(i - 1)
+}
+
+/// カンマ区切りの兵力を読み取る。足りない分は 0 で埋める。
+fn parse_forces<const CAP: usize>(s: &str) -> anyhow::Result<ArrayVec<FleetForce, CAP>> {
+    let mut forces = ArrayVec::<FleetForce, CAP>::new();
+
+    for x in s.split(',').filter(|x| !x.is_empty()) {
+        let force = x.parse()?;
+        forces
+            .try_push(force)
+            .map_err(|_| anyhow::anyhow!("too many fleets (max {CAP})"))?;
+    }
+
+    while !forces.is_full() {
+        forces.push(FleetForce::zero());
+    }
+
+    Ok(forces)
+}
+
+/// 兵力をカンマ区切りで並べる。末尾の 0 は省略する (全て 0 なら "0")。
+fn join_forces(forces: &[FleetForce]) -> String {
+    let len = forces
+        .iter()
+        .rposition(|force| !force.is_zero())
+        .map_or(1, |i| i + 1);
+
+    forces[..len]
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 疲労中の艦隊があれば ` tired=...` を書く。
+fn write_tireds(
+    f: &mut std::fmt::Formatter,
+    fleet_is_tireds: &[bool],
+    guard_is_tired: bool,
+) -> std::fmt::Result {
+    let tireds: Vec<_> = guard_is_tired
+        .then(|| "guard".to_owned())
+        .into_iter()
+        .chain(
+            (0..fleet_is_tireds.len())
+                .filter(|&i| fleet_is_tireds[i])
+                .map(|i| (i + 1).to_string()),
+        )
+        .collect();
+    if !tireds.is_empty() {
+        write!(f, " tired={}", tireds.join(","))?;
+    }
+
+    Ok(())
+}
+
+/// 能力を ` yang` または ` ability=...` の形で書く。
+fn write_abilities(f: &mut std::fmt::Formatter, abilities: &[Ability]) -> std::fmt::Result {
+    for ability in abilities {
+        if *ability == Ability::YANG {
+            write!(f, " yang")?;
+        } else {
+            write!(f, " ability={ability}")?;
+        }
+    }
+
+    Ok(())
+}
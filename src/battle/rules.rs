@@ -0,0 +1,196 @@
+//! 戦闘計算の規則 (ゲームのバージョンや改造によって変わりうる値)。
+
+use anyhow::ensure;
+
+use super::*;
+
+/// 攻撃可能な総兵力の clamp 範囲の上限として設定できる最大値 (1 陣営が持ちうる最大の総兵力)。
+pub const ATTACK_FORCE_LIMIT: u32 = FleetForce::MAX.inner() * FLEET_COUNT_MAX as u32;
+
+/// フォーメーション係数として設定できる最大値。
+pub const ATTACK_COEF_MAX: u32 = 100;
+
+/// フォーメーション修正のしきい値として設定できる最大値。
+///
+/// 判定値は 1 艦隊あたりの兵力の 1/10 以下なので、これ以上のしきい値は常に強制されるのと同じ。
+pub const FORMATION_RATIO_FORCED_MAX: u32 = FleetForce::MAX.inner() / 10;
+
+/// ダメージ計算の除数として設定できる最大値。
+pub const DAMAGE_DIVISOR_MAX: u32 = 1000;
+
+/// 全滅判定のしきい値として設定できる最大値 (これなら必ず全滅する)。
+pub const ANNIHILATION_MAX: u32 = FleetForce::MAX.inner();
+
+/// 戦闘計算の規則。
+///
+/// デフォルトは FC 版の値。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleSet {
+    attack_force_min: u32,
+    attack_force_max: u32,
+    attack_coefs: [[u32; Formation::COUNT]; Formation::COUNT],
+    formation_ratio_forced: u32,
+    damage_divisor: u32,
+    annihilation_fleet: u32,
+    annihilation_guard: u32,
+    yang: Ability,
+}
+
+impl RuleSet {
+    /// 攻撃可能な総兵力の clamp 範囲の下限を返す。
+    pub fn attack_force_min(&self) -> u32 {
+        self.attack_force_min
+    }
+
+    /// 攻撃可能な総兵力の clamp 範囲の上限を返す。
+    pub fn attack_force_max(&self) -> u32 {
+        self.attack_force_max
+    }
+
+    /// 攻撃可能な総兵力の clamp 範囲を設定する。min > max の場合、または max が
+    /// `ATTACK_FORCE_LIMIT` を超える場合はエラーを返す。
+    pub fn set_attack_force_range(&mut self, min: u32, max: u32) -> anyhow::Result<()> {
+        ensure!(min <= max, "attack force range is empty: {min}..={max}");
+        ensure!(
+            max <= ATTACK_FORCE_LIMIT,
+            "attack force max should be at most {ATTACK_FORCE_LIMIT}: {max}"
+        );
+
+        self.attack_force_min = min;
+        self.attack_force_max = max;
+
+        Ok(())
+    }
+
+    /// 自陣営フォーメーション us, 相手陣営フォーメーション them のときのフォーメーション係数を返す。
+    pub fn attack_coef(&self, us: Formation, them: Formation) -> u32 {
+        self.attack_coefs[us.index()][them.index()]
+    }
+
+    /// フォーメーション係数を設定する。`ATTACK_COEF_MAX` を超える場合はエラーを返す。
+    pub fn set_attack_coef(
+        &mut self,
+        us: Formation,
+        them: Formation,
+        coef: u32,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            coef <= ATTACK_COEF_MAX,
+            "attack coef should be at most {ATTACK_COEF_MAX}: {coef}"
+        );
+
+        self.attack_coefs[us.index()][them.index()] = coef;
+
+        Ok(())
+    }
+
+    /// フォーメーション修正の判定値がこれ以下ならフォーメーション 0 が強制される。
+    pub fn formation_ratio_forced(&self) -> u32 {
+        self.formation_ratio_forced
+    }
+
+    /// フォーメーション修正のしきい値を設定する。`FORMATION_RATIO_FORCED_MAX` を超える場合は
+    /// エラーを返す。
+    pub fn set_formation_ratio_forced(
+        &mut self,
+        formation_ratio_forced: u32,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            formation_ratio_forced <= FORMATION_RATIO_FORCED_MAX,
+            "formation ratio forced should be at most {FORMATION_RATIO_FORCED_MAX}: {formation_ratio_forced}"
+        );
+
+        self.formation_ratio_forced = formation_ratio_forced;
+
+        Ok(())
+    }
+
+    /// 1 艦隊あたりのダメージ計算で、健在な艦隊数に掛ける値を返す。
+    pub fn damage_divisor(&self) -> u32 {
+        self.damage_divisor
+    }
+
+    /// 1 艦隊あたりのダメージ計算で、健在な艦隊数に掛ける値を設定する。0 の場合、または
+    /// `DAMAGE_DIVISOR_MAX` を超える場合はエラーを返す。
+    pub fn set_damage_divisor(&mut self, damage_divisor: u32) -> anyhow::Result<()> {
+        ensure!(
+            (1..=DAMAGE_DIVISOR_MAX).contains(&damage_divisor),
+            "damage divisor should be in 1..={DAMAGE_DIVISOR_MAX}: {damage_divisor}"
+        );
+
+        self.damage_divisor = damage_divisor;
+
+        Ok(())
+    }
+
+    /// 通常艦隊の全滅判定のしきい値 (ダメージ適用後の兵力がこれ以下なら全滅) を返す。
+    pub fn annihilation_fleet(&self) -> u32 {
+        self.annihilation_fleet
+    }
+
+    /// 通常艦隊の全滅判定のしきい値を設定する。`ANNIHILATION_MAX` を超える場合はエラーを返す。
+    pub fn set_annihilation_fleet(&mut self, annihilation_fleet: u32) -> anyhow::Result<()> {
+        ensure!(
+            annihilation_fleet <= ANNIHILATION_MAX,
+            "annihilation fleet should be at most {ANNIHILATION_MAX}: {annihilation_fleet}"
+        );
+
+        self.annihilation_fleet = annihilation_fleet;
+
+        Ok(())
+    }
+
+    /// 駐留艦隊の全滅判定のしきい値を返す。
+    pub fn annihilation_guard(&self) -> u32 {
+        self.annihilation_guard
+    }
+
+    /// 駐留艦隊の全滅判定のしきい値を設定する。`ANNIHILATION_MAX` を超える場合はエラーを返す。
+    pub fn set_annihilation_guard(&mut self, annihilation_guard: u32) -> anyhow::Result<()> {
+        ensure!(
+            annihilation_guard <= ANNIHILATION_MAX,
+            "annihilation guard should be at most {ANNIHILATION_MAX}: {annihilation_guard}"
+        );
+
+        self.annihilation_guard = annihilation_guard;
+
+        Ok(())
+    }
+
+    /// ヤンの補正を返す。
+    ///
+    /// query の能力のうち `Ability::YANG` と等しいもの (簡易記法の `yang` など) は、戦闘計算の際に
+    /// この値に置き換えられる。
+    pub fn yang(&self) -> Ability {
+        self.yang
+    }
+
+    pub fn set_yang(&mut self, yang: Ability) {
+        self.yang = yang;
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            attack_force_min: 100,
+            attack_force_max: 1600,
+            attack_coefs: [
+                [3, 1, 1, 1, 1, 1, 1, 1],
+                [5, 4, 3, 5, 4, 2, 2, 4],
+                [5, 3, 2, 3, 4, 2, 1, 3],
+                [5, 3, 3, 4, 4, 4, 3, 5],
+                [5, 4, 2, 4, 5, 4, 5, 5],
+                [5, 4, 3, 3, 3, 3, 2, 2],
+                [5, 2, 3, 3, 3, 1, 1, 3],
+                [5, 4, 4, 2, 5, 5, 3, 5],
+            ],
+            formation_ratio_forced: 3,
+            damage_divisor: 12,
+            annihilation_fleet: 8,
+            // 通常艦隊より 1 小さい (兵力 8 なら生き残る)。
+            annihilation_guard: 7,
+            yang: Ability::YANG,
+        }
+    }
+}
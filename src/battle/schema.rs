@@ -0,0 +1,483 @@
+//! `Query`, `Report`, `RuleSet` の serde 表現。
+//!
+//! JSON では以下の形になる (`version` は現在 2)。
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "ally": {
+//!     "fleet_forces": [100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+//!     "fleet_is_tireds": [false, false, false, false, false, false, false, false, false, false, false],
+//!     "formation": 1,
+//!     "abilities": []
+//!   },
+//!   "enemy": {
+//!     "fleet_forces": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+//!     "fleet_is_tireds": [false, false, false, false, false, false, false, false, false, false, false, false, false, false, false],
+//!     "guard_force": 100,
+//!     "guard_is_tired": false,
+//!     "formation": 1,
+//!     "abilities": [
+//!       { "attack_bonus": 10, "defense_divisor": 2, "defense_offset": 1, "exempt": [5] }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! `Report` は以下の形になる。
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "ally": { "formation": 1, "damage_per_fleet": 33, "fleet_forces": [67, 0, ...] },
+//!   "enemy": { "formation": 1, "damage_per_fleet": 33, "fleet_forces": [0, ...], "guard_force": 67 }
+//! }
+//! ```
+//!
+//! 艦隊の配列は要素数が上限 (`ALLY_FLEET_COUNT`, `ENEMY_FLEET_COUNT`) 以下であればよく、
+//! 足りない分は兵力 0 (疲労なし) とみなす。上限を超える場合や値が範囲外の場合はエラーになる。
+//! 未知のフィールドもエラーになる。
+//!
+//! 敵の `fleet_is_tireds`, `guard_is_tired` は後から追加したもので、省略すると疲労なしとみなす。
+//!
+//! バージョン 1 の入力も読み込める。バージョン 1 では `abilities` の代わりに敵の `has_yang`
+//! (ヤン参戦) を使う。
+//!
+//! `RuleSet` は以下の形になる (値は FC 版のもの)。バージョンは持たない。省略したフィールドは
+//! FC 版の値になるので、変更したい値だけを書けばよい。
+//!
+//! ```json
+//! {
+//!   "attack_force_min": 100,
+//!   "attack_force_max": 1600,
+//!   "attack_coefs": [[3, 1, 1, 1, 1, 1, 1, 1], [5, 4, 3, 5, 4, 2, 2, 4], ...],
+//!   "formation_ratio_forced": 3,
+//!   "damage_divisor": 12,
+//!   "annihilation_fleet": 8,
+//!   "annihilation_guard": 7,
+//!   "yang": { "attack_bonus": 10, "defense_divisor": 2, "defense_offset": 1, "exempt": [5] }
+//! }
+//! ```
+//!
+//! `attack_force_max` は `ATTACK_FORCE_LIMIT` 以下、`attack_coefs` の各値は `ATTACK_COEF_MAX` 以下、
+//! `formation_ratio_forced` は `FORMATION_RATIO_FORCED_MAX` 以下、`damage_divisor` は 1 以上
+//! `DAMAGE_DIVISOR_MAX` 以下、`annihilation_fleet` と `annihilation_guard` は `ANNIHILATION_MAX`
+//! 以下でなければならない。`yang` は query のヤン (`yang`) に使われる補正。
+//!
+//! `attack_coefs` は自陣営フォーメーションを行、相手陣営フォーメーションを列とする
+//! `Formation::COUNT` 行 `Formation::COUNT` 列の表。
+
+use anyhow::{bail, ensure, Context as _};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::*;
+
+/// スキーマのバージョン。互換性のない変更をしたら上げる。
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// 読み込めるスキーマの最小バージョン。
+const SCHEMA_VERSION_MIN: u32 = 1;
+
+impl Serialize for Query {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        QueryRepr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Query {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = QueryRepr::deserialize(deserializer)?;
+
+        Self::try_from(repr).map_err(|e| D::Error::custom(format!("{e:#}")))
+    }
+}
+
+impl Serialize for Report {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ReportRepr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Report {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ReportRepr::deserialize(deserializer)?;
+
+        Self::try_from(repr).map_err(|e| D::Error::custom(format!("{e:#}")))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct QueryRepr {
+    version: u32,
+    ally: QueryAllyRepr,
+    enemy: QueryEnemyRepr,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct QueryAllyRepr {
+    fleet_forces: Vec<FleetForce>,
+    fleet_is_tireds: Vec<bool>,
+    formation: Formation,
+    #[serde(default)]
+    abilities: Vec<AbilityRepr>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct QueryEnemyRepr {
+    fleet_forces: Vec<FleetForce>,
+    #[serde(default)]
+    fleet_is_tireds: Vec<bool>,
+    guard_force: FleetForce,
+    #[serde(default)]
+    guard_is_tired: bool,
+    formation: Formation,
+    #[serde(default)]
+    abilities: Vec<AbilityRepr>,
+    /// バージョン 1 のみ。
+    #[serde(default, skip_serializing)]
+    has_yang: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct AbilityRepr {
+    attack_bonus: u32,
+    defense_divisor: u32,
+    defense_offset: u32,
+    #[serde(default)]
+    exempt: Vec<Formation>,
+}
+
+impl From<&Ability> for AbilityRepr {
+    fn from(ability: &Ability) -> Self {
+        Self {
+            attack_bonus: ability.attack_bonus(),
+            defense_divisor: ability.defense_divisor(),
+            defense_offset: ability.defense_offset(),
+            exempt: Formation::all()
+                .filter(|&formation| ability.is_exempt(formation))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<AbilityRepr> for Ability {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: AbilityRepr) -> Result<Self, Self::Error> {
+        let ability = Ability::new(repr.attack_bonus, repr.defense_divisor, repr.defense_offset)
+            .with_context(|| {
+                format!(
+                    "defense_divisor should be nonzero, and attack_bonus and defense_offset should be at most {ABILITY_VALUE_MAX}"
+                )
+            })?;
+
+        Ok(repr
+            .exempt
+            .into_iter()
+            .fold(ability, |ability, formation| ability.with_exempt(formation)))
+    }
+}
+
+impl From<&Query> for QueryRepr {
+    fn from(query: &Query) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            ally: QueryAllyRepr {
+                fleet_forces: query.ally.fleet_forces.to_vec(),
+                fleet_is_tireds: query.ally.fleet_is_tireds.to_vec(),
+                formation: query.ally.formation,
+                abilities: query.ally.abilities.iter().map(AbilityRepr::from).collect(),
+            },
+            enemy: QueryEnemyRepr {
+                fleet_forces: query.enemy.fleet_forces.to_vec(),
+                fleet_is_tireds: query.enemy.fleet_is_tireds.to_vec(),
+                guard_force: query.enemy.guard_force,
+                guard_is_tired: query.enemy.guard_is_tired,
+                formation: query.enemy.formation,
+                abilities: query
+                    .enemy
+                    .abilities
+                    .iter()
+                    .map(AbilityRepr::from)
+                    .collect(),
+                has_yang: false,
+            },
+        }
+    }
+}
+
+impl TryFrom<QueryRepr> for Query {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: QueryRepr) -> Result<Self, Self::Error> {
+        ensure_version(repr.version)?;
+
+        if repr.version < 2 {
+            ensure!(
+                repr.ally.abilities.is_empty() && repr.enemy.abilities.is_empty(),
+                "abilities is not supported in schema version {}",
+                repr.version
+            );
+        } else {
+            ensure!(
+                !repr.enemy.has_yang,
+                "enemy.has_yang is only supported in schema version 1 (use enemy.abilities)"
+            );
+        }
+
+        let mut enemy_abilities = to_abilities(repr.enemy.abilities)?;
+        if repr.enemy.has_yang {
+            enemy_abilities.push(Ability::YANG);
+        }
+
+        Ok(Self {
+            ally: QueryAlly {
+                fleet_forces: to_array_vec(
+                    repr.ally.fleet_forces,
+                    FleetForce::zero(),
+                    "ally.fleet_forces",
+                )?,
+                fleet_is_tireds: to_array_vec(
+                    repr.ally.fleet_is_tireds,
+                    false,
+                    "ally.fleet_is_tireds",
+                )?,
+                formation: repr.ally.formation,
+                abilities: to_abilities(repr.ally.abilities)?,
+            },
+            enemy: QueryEnemy {
+                fleet_forces: to_array_vec(
+                    repr.enemy.fleet_forces,
+                    FleetForce::zero(),
+                    "enemy.fleet_forces",
+                )?,
+                fleet_is_tireds: to_array_vec(
+                    repr.enemy.fleet_is_tireds,
+                    false,
+                    "enemy.fleet_is_tireds",
+                )?,
+                guard_force: repr.enemy.guard_force,
+                guard_is_tired: repr.enemy.guard_is_tired,
+                formation: repr.enemy.formation,
+                abilities: enemy_abilities,
+            },
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ReportRepr {
+    version: u32,
+    ally: ReportAllyRepr,
+    enemy: ReportEnemyRepr,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ReportAllyRepr {
+    formation: Formation,
+    damage_per_fleet: u32,
+    fleet_forces: Vec<FleetForce>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ReportEnemyRepr {
+    formation: Formation,
+    damage_per_fleet: u32,
+    fleet_forces: Vec<FleetForce>,
+    guard_force: FleetForce,
+}
+
+impl From<&Report> for ReportRepr {
+    fn from(report: &Report) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            ally: ReportAllyRepr {
+                formation: report.ally.formation,
+                damage_per_fleet: report.ally.damage_per_fleet,
+                fleet_forces: report.ally.fleet_forces.to_vec(),
+            },
+            enemy: ReportEnemyRepr {
+                formation: report.enemy.formation,
+                damage_per_fleet: report.enemy.damage_per_fleet,
+                fleet_forces: report.enemy.fleet_forces.to_vec(),
+                guard_force: report.enemy.guard_force,
+            },
+        }
+    }
+}
+
+impl TryFrom<ReportRepr> for Report {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: ReportRepr) -> Result<Self, Self::Error> {
+        ensure_version(repr.version)?;
+
+        for (name, damage_per_fleet) in [
+            ("ally.damage_per_fleet", repr.ally.damage_per_fleet),
+            ("enemy.damage_per_fleet", repr.enemy.damage_per_fleet),
+        ] {
+            ensure!(
+                damage_per_fleet <= DAMAGE_PER_FLEET_MAX,
+                "{name} is out of range: {damage_per_fleet} (max {DAMAGE_PER_FLEET_MAX})"
+            );
+        }
+
+        Ok(Self {
+            ally: ReportAlly {
+                formation: repr.ally.formation,
+                damage_per_fleet: repr.ally.damage_per_fleet,
+                fleet_forces: to_array_vec(
+                    repr.ally.fleet_forces,
+                    FleetForce::zero(),
+                    "ally.fleet_forces",
+                )?,
+            },
+            enemy: ReportEnemy {
+                formation: repr.enemy.formation,
+                damage_per_fleet: repr.enemy.damage_per_fleet,
+                fleet_forces: to_array_vec(
+                    repr.enemy.fleet_forces,
+                    FleetForce::zero(),
+                    "enemy.fleet_forces",
+                )?,
+                guard_force: repr.enemy.guard_force,
+            },
+        })
+    }
+}
+
+impl Serialize for RuleSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RuleSetRepr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RuleSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = RuleSetRepr::deserialize(deserializer)?;
+
+        Self::try_from(repr).map_err(|e| D::Error::custom(format!("{e:#}")))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct RuleSetRepr {
+    attack_force_min: u32,
+    attack_force_max: u32,
+    attack_coefs: Vec<Vec<u32>>,
+    formation_ratio_forced: u32,
+    damage_divisor: u32,
+    annihilation_fleet: u32,
+    annihilation_guard: u32,
+    yang: AbilityRepr,
+}
+
+impl Default for RuleSetRepr {
+    fn default() -> Self {
+        Self::from(&RuleSet::default())
+    }
+}
+
+impl From<&RuleSet> for RuleSetRepr {
+    fn from(rules: &RuleSet) -> Self {
+        Self {
+            attack_force_min: rules.attack_force_min(),
+            attack_force_max: rules.attack_force_max(),
+            attack_coefs: Formation::all()
+                .map(|us| {
+                    Formation::all()
+                        .map(|them| rules.attack_coef(us, them))
+                        .collect()
+                })
+                .collect(),
+            formation_ratio_forced: rules.formation_ratio_forced(),
+            damage_divisor: rules.damage_divisor(),
+            annihilation_fleet: rules.annihilation_fleet(),
+            annihilation_guard: rules.annihilation_guard(),
+            yang: AbilityRepr::from(&rules.yang()),
+        }
+    }
+}
+
+impl TryFrom<RuleSetRepr> for RuleSet {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: RuleSetRepr) -> Result<Self, Self::Error> {
+        let mut rules = RuleSet::default();
+
+        rules.set_attack_force_range(repr.attack_force_min, repr.attack_force_max)?;
+
+        ensure!(
+            repr.attack_coefs.len() == Formation::COUNT,
+            "attack_coefs should have {} rows: {}",
+            Formation::COUNT,
+            repr.attack_coefs.len()
+        );
+        for (us, row) in Formation::all().zip(repr.attack_coefs) {
+            ensure!(
+                row.len() == Formation::COUNT,
+                "attack_coefs row {us} should have {} columns: {}",
+                Formation::COUNT,
+                row.len()
+            );
+            for (them, coef) in Formation::all().zip(row) {
+                rules
+                    .set_attack_coef(us, them, coef)
+                    .with_context(|| format!("invalid attack_coefs[{us}][{them}]"))?;
+            }
+        }
+
+        rules.set_formation_ratio_forced(repr.formation_ratio_forced)?;
+        rules.set_damage_divisor(repr.damage_divisor)?;
+        rules.set_annihilation_fleet(repr.annihilation_fleet)?;
+        rules.set_annihilation_guard(repr.annihilation_guard)?;
+        rules.set_yang(Ability::try_from(repr.yang).context("invalid yang")?);
+
+        Ok(rules)
+    }
+}
+
+fn ensure_version(version: u32) -> anyhow::Result<()> {
+    if !(SCHEMA_VERSION_MIN..=SCHEMA_VERSION).contains(&version) {
+        bail!(
+            "unsupported schema version: {version} (expected {SCHEMA_VERSION_MIN}..={SCHEMA_VERSION})"
+        );
+    }
+
+    Ok(())
+}
+
+fn to_abilities(reprs: Vec<AbilityRepr>) -> anyhow::Result<Vec<Ability>> {
+    reprs.into_iter().map(Ability::try_from).collect()
+}
+
+/// 艦隊の配列を長さ CAP の `ArrayVec` に変換する。足りない要素は fill で埋める。
+fn to_array_vec<T, const CAP: usize>(
+    xs: Vec<T>,
+    fill: T,
+    name: &str,
+) -> anyhow::Result<ArrayVec<T, CAP>>
+where
+    T: Clone,
+{
+    ensure!(
+        xs.len() <= CAP,
+        "{name} has too many elements: {} (max {CAP})",
+        xs.len()
+    );
+
+    let mut res: ArrayVec<T, CAP> = xs.into_iter().collect();
+    while !res.is_full() {
+        res.push(fill.clone());
+    }
+
+    Ok(res)
+}
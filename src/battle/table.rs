@@ -0,0 +1,298 @@
+//! 入力空間全体を列挙した戦闘結果の表とその統計。
+//!
+//! 1 艦隊あたりのダメージは、攻撃側の総兵力 (clamp 後、100 単位に切り捨て)、両陣営の修正後
+//! フォーメーション、防御側の艦隊数、ヤンの有無だけで決まる。これらの組み合わせは有限なので、
+//! 全てを列挙した表を作れる。
+//!
+//! ヤンの補正は相手陣営の (修正前の) フォーメーションによっては無効になる。表のフォーメーションは
+//! 修正後のものなので、ヤンの補正が無効になる組み合わせ (修正前後で同じフォーメーションが
+//! 補正を無効にするもの) は実際には起こらないとして表から除く。修正後がフォーメーション 0 の場合、
+//! 修正前はどのフォーメーションでもありうるので、いずれかで補正が有効なら残す。
+//!
+//! フォーメーション修正 (フォーメーション 0 の強制) は艦隊数と (clamp 前の) 総兵力だけで
+//! 決まるので、こちらも艦隊数ごとに全ての総兵力を列挙する。
+
+use super::*;
+
+/// ヤンがどちらの陣営にいるか。
+///
+/// 表では有効なヤンだけを扱う (相手のフォーメーションにより無効になる場合は `None` とみなす)。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum YangSide {
+    None,
+    Attacker,
+    Defender,
+}
+
+impl YangSide {
+    pub const ALL: [Self; 3] = [Self::None, Self::Attacker, Self::Defender];
+
+    /// rules のヤンの補正について、(攻撃側の能力, 防御側の能力) を返す。
+    fn abilities(self, rules: &RuleSet) -> (Vec<Ability>, Vec<Ability>) {
+        match self {
+            Self::None => (vec![], vec![]),
+            Self::Attacker => (vec![rules.yang()], vec![]),
+            Self::Defender => (vec![], vec![rules.yang()]),
+        }
+    }
+
+    /// 両陣営の修正後フォーメーションがこれらのとき、ヤンの補正が有効な状態で起こりうるかを返す。
+    fn is_possible(
+        self,
+        attacker_formation: Formation,
+        defender_formation: Formation,
+        rules: &RuleSet,
+    ) -> bool {
+        let is_effective_against = |formation_them: Formation| {
+            if formation_them.is_broken() {
+                // 修正前はどのフォーメーションでもありうる。
+                Formation::all().any(|formation| !rules.yang().is_exempt(formation))
+            } else {
+                !rules.yang().is_exempt(formation_them)
+            }
+        };
+
+        match self {
+            Self::None => true,
+            Self::Attacker => is_effective_against(defender_formation),
+            Self::Defender => is_effective_against(attacker_formation),
+        }
+    }
+}
+
+impl std::fmt::Display for YangSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::None => "none",
+            Self::Attacker => "attacker",
+            Self::Defender => "defender",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/// 1 艦隊あたりのダメージの表の 1 行。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DamageEntry {
+    attack_force: u32,
+    attacker_formation: Formation,
+    defender_formation: Formation,
+    defender_fleet_count: usize,
+    yang: YangSide,
+    attack: u32,
+    damage_per_fleet: u32,
+}
+
+impl DamageEntry {
+    /// 攻撃側の総兵力 (clamp 後、100 単位に切り捨て) を返す。
+    pub fn attack_force(&self) -> u32 {
+        self.attack_force
+    }
+
+    /// 攻撃側の修正後フォーメーションを返す。
+    pub fn attacker_formation(&self) -> Formation {
+        self.attacker_formation
+    }
+
+    /// 防御側の修正後フォーメーションを返す。
+    pub fn defender_formation(&self) -> Formation {
+        self.defender_formation
+    }
+
+    /// 防御側の艦隊数 (駐留艦隊を含む) を返す。
+    pub fn defender_fleet_count(&self) -> usize {
+        self.defender_fleet_count
+    }
+
+    pub fn yang(&self) -> YangSide {
+        self.yang
+    }
+
+    /// 攻撃力 (能力補正済み) を返す。
+    pub fn attack(&self) -> u32 {
+        self.attack
+    }
+
+    /// 防御側の 1 艦隊あたりのダメージを返す。
+    pub fn damage_per_fleet(&self) -> u32 {
+        self.damage_per_fleet
+    }
+}
+
+/// 1 艦隊あたりのダメージの表を作る。
+///
+/// 行は (攻撃側の総兵力, 攻撃側フォーメーション, 防御側フォーメーション, 防御側の艦隊数, ヤン)
+/// の辞書順に並ぶ。ヤンの補正が無効になる組み合わせは含まない。
+pub fn damage_table(rules: &RuleSet) -> Vec<DamageEntry> {
+    let attack_forces =
+        (rules.attack_force_min() / 100..=rules.attack_force_max() / 100).map(|n| 100 * n);
+
+    let mut entries = Vec::<DamageEntry>::new();
+
+    for attack_force in attack_forces {
+        for attacker_formation in Formation::all() {
+            for defender_formation in Formation::all() {
+                let attack_raw =
+                    calc_attack_raw(attack_force, attacker_formation, defender_formation, rules);
+
+                for defender_fleet_count in 1..=FLEET_COUNT_MAX {
+                    for yang in YangSide::ALL {
+                        if !yang.is_possible(attacker_formation, defender_formation, rules) {
+                            continue;
+                        }
+
+                        let (abilities_attacker, abilities_defender) = yang.abilities(rules);
+                        let (attack, _) =
+                            adjust_attacks(attack_raw, 0, &abilities_attacker, &abilities_defender);
+
+                        entries.push(DamageEntry {
+                            attack_force,
+                            attacker_formation,
+                            defender_formation,
+                            defender_fleet_count,
+                            yang,
+                            attack,
+                            damage_per_fleet: calc_damage_per_fleet(
+                                attack,
+                                defender_fleet_count,
+                                rules,
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// フォーメーション修正の表の 1 行 (艦隊数 1 つ分)。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CollapseEntry {
+    fleet_count: usize,
+    attack_force_count: u32,
+    collapsed_count: u32,
+    attack_force_min_uncollapsed: Option<u32>,
+}
+
+impl CollapseEntry {
+    pub fn fleet_count(&self) -> usize {
+        self.fleet_count
+    }
+
+    /// 列挙した総兵力 (0 から艦隊数 × `FleetForce::MAX` まで) の個数を返す。
+    pub fn attack_force_count(&self) -> u32 {
+        self.attack_force_count
+    }
+
+    /// そのうちフォーメーション 0 が強制される総兵力の個数を返す。
+    pub fn collapsed_count(&self) -> u32 {
+        self.collapsed_count
+    }
+
+    /// フォーメーション 0 が強制されない最小の総兵力を返す。どの総兵力でも強制されるなら `None`。
+    pub fn attack_force_min_uncollapsed(&self) -> Option<u32> {
+        self.attack_force_min_uncollapsed
+    }
+}
+
+/// 艦隊数 1 から `FLEET_COUNT_MAX` までについて、フォーメーション修正の表を作る。
+///
+/// 疲労した艦隊も艦隊数には数えるので、総兵力は 0 から艦隊数 × `FleetForce::MAX` までの
+/// 全ての値を取りうる。
+pub fn collapse_table(rules: &RuleSet) -> Vec<CollapseEntry> {
+    (1..=FLEET_COUNT_MAX)
+        .map(|fleet_count| {
+            let attack_force_max = util::u32_from_usize(fleet_count) * FleetForce::MAX.inner();
+            let is_collapsed =
+                |attack_force: u32| formation_is_forced(fleet_count, attack_force, rules);
+
+            let collapsed_count = (0..=attack_force_max)
+                .filter(|&attack_force| is_collapsed(attack_force))
+                .count();
+
+            CollapseEntry {
+                fleet_count,
+                attack_force_count: attack_force_max + 1,
+                collapsed_count: util::u32_from_usize(collapsed_count),
+                attack_force_min_uncollapsed: (0..=attack_force_max)
+                    .find(|&attack_force| !is_collapsed(attack_force)),
+            }
+        })
+        .collect()
+}
+
+/// 表全体の統計。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TableStats {
+    damage_entry_count: u32,
+    damage_capped_count: u32,
+    damage_zero_count: u32,
+    collapse_config_count: u32,
+    collapsed_count: u32,
+}
+
+impl TableStats {
+    pub fn new(damage_entries: &[DamageEntry], collapse_entries: &[CollapseEntry]) -> Self {
+        let count_damage = |f: fn(u32) -> bool| {
+            let count = damage_entries
+                .iter()
+                .filter(|entry| f(entry.damage_per_fleet))
+                .count();
+            util::u32_from_usize(count)
+        };
+
+        Self {
+            damage_entry_count: util::u32_from_usize(damage_entries.len()),
+            damage_capped_count: count_damage(|damage| damage >= DAMAGE_PER_FLEET_MAX),
+            damage_zero_count: count_damage(|damage| damage == 0),
+            collapse_config_count: collapse_entries
+                .iter()
+                .map(|entry| entry.attack_force_count)
+                .sum(),
+            collapsed_count: collapse_entries
+                .iter()
+                .map(|entry| entry.collapsed_count)
+                .sum(),
+        }
+    }
+
+    /// ダメージの表の行数を返す。
+    pub fn damage_entry_count(&self) -> u32 {
+        self.damage_entry_count
+    }
+
+    /// 1 艦隊あたりのダメージが上限に達する行の割合を返す。
+    pub fn damage_capped_fraction(&self) -> f64 {
+        fraction(self.damage_capped_count, self.damage_entry_count)
+    }
+
+    /// 1 艦隊あたりのダメージが 0 になる行の割合を返す。
+    pub fn damage_zero_fraction(&self) -> f64 {
+        fraction(self.damage_zero_count, self.damage_entry_count)
+    }
+
+    /// フォーメーション修正の表で列挙した (艦隊数, 総兵力) の組の数を返す。
+    pub fn collapse_config_count(&self) -> u32 {
+        self.collapse_config_count
+    }
+
+    /// (艦隊数, 総兵力) の組のうち、フォーメーション 0 が強制されるものの割合を返す。
+    pub fn collapsed_fraction(&self) -> f64 {
+        fraction(self.collapsed_count, self.collapse_config_count)
+    }
+}
+
+fn fraction(numer: u32, denom: u32) -> f64 {
+    f64::from(numer) / f64::from(denom)
+}
@@ -0,0 +1,195 @@
+use super::*;
+
+/// 戦闘結果とともに、その計算過程 (中間値すべて) を返す。
+///
+/// 返される `Report` は `battle_simulate()` のものと同一。
+pub fn battle_simulate_traced(query: &Query, rules: &RuleSet) -> Option<(Report, Trace)> {
+    let report = battle_simulate(query, rules)?;
+
+    let ally_attack_force = query.ally.attack_force();
+    let ally_fleet_count = query.ally.fleet_count();
+    let ally_formation_ratio = formation_ratio(ally_fleet_count, ally_attack_force, rules);
+    let ally_formation = query.ally.formation_modified(rules);
+
+    let enemy_attack_force = query.enemy.attack_force();
+    let enemy_fleet_count = query.enemy.fleet_count();
+    let enemy_formation_ratio = formation_ratio(enemy_fleet_count, enemy_attack_force, rules);
+    let enemy_formation = query.enemy.formation_modified(rules);
+
+    let ally_attack_coef = rules.attack_coef(ally_formation, enemy_formation);
+    let enemy_attack_coef = rules.attack_coef(enemy_formation, ally_formation);
+
+    let ally_attack_raw = calc_attack_raw(
+        query.ally.attack_force_clamped(rules),
+        ally_formation,
+        enemy_formation,
+        rules,
+    );
+    let enemy_attack_raw = calc_attack_raw(
+        query.enemy.attack_force_clamped(rules),
+        enemy_formation,
+        ally_formation,
+        rules,
+    );
+
+    let ally_abilities_effective = query.ally_abilities_effective(rules);
+    let enemy_abilities_effective = query.enemy_abilities_effective(rules);
+    let (ally_attack, enemy_attack) = calc_attacks(query, rules);
+
+    let ally_damage_per_fleet =
+        calc_damage_per_fleet_uncapped(enemy_attack, ally_fleet_count, rules);
+    let enemy_damage_per_fleet =
+        calc_damage_per_fleet_uncapped(ally_attack, enemy_fleet_count, rules);
+
+    let ally_fleets = query
+        .ally
+        .fleet_forces
+        .iter()
+        .map(|&force| {
+            TraceFleet::new(
+                force,
+                report.ally.damage_per_fleet,
+                rules.annihilation_fleet(),
+            )
+        })
+        .collect();
+    let enemy_fleets = query
+        .enemy
+        .fleet_forces
+        .iter()
+        .map(|&force| {
+            TraceFleet::new(
+                force,
+                report.enemy.damage_per_fleet,
+                rules.annihilation_fleet(),
+            )
+        })
+        .collect();
+    let enemy_guard = TraceFleet::new(
+        query.enemy.guard_force,
+        report.enemy.damage_per_fleet,
+        rules.annihilation_guard(),
+    );
+
+    let trace = Trace {
+        ally: TraceSide {
+            fleet_count: ally_fleet_count,
+            attack_force: ally_attack_force,
+            attack_force_clamped: query.ally.attack_force_clamped(rules),
+            formation: query.ally.formation,
+            formation_ratio: ally_formation_ratio,
+            formation_modified: ally_formation,
+            formation_is_forced: formation_is_forced(ally_fleet_count, ally_attack_force, rules),
+            attack_coef: ally_attack_coef,
+            attack_raw: ally_attack_raw,
+            abilities_effective: ally_abilities_effective,
+            attack: ally_attack,
+            damage_numer: 100 * u64::from(enemy_attack),
+            damage_denom: u64::from(rules.damage_divisor())
+                * util::u64_from_usize(ally_fleet_count),
+            damage_per_fleet_uncapped: ally_damage_per_fleet,
+            damage_per_fleet: report.ally.damage_per_fleet,
+            fleets: ally_fleets,
+            guard: None,
+        },
+        enemy: TraceSide {
+            fleet_count: enemy_fleet_count,
+            attack_force: enemy_attack_force,
+            attack_force_clamped: query.enemy.attack_force_clamped(rules),
+            formation: query.enemy.formation,
+            formation_ratio: enemy_formation_ratio,
+            formation_modified: enemy_formation,
+            formation_is_forced: formation_is_forced(enemy_fleet_count, enemy_attack_force, rules),
+            attack_coef: enemy_attack_coef,
+            attack_raw: enemy_attack_raw,
+            abilities_effective: enemy_abilities_effective,
+            attack: enemy_attack,
+            damage_numer: 100 * u64::from(ally_attack),
+            damage_denom: u64::from(rules.damage_divisor())
+                * util::u64_from_usize(enemy_fleet_count),
+            damage_per_fleet_uncapped: enemy_damage_per_fleet,
+            damage_per_fleet: report.enemy.damage_per_fleet,
+            fleets: enemy_fleets,
+            guard: Some(enemy_guard),
+        },
+    };
+
+    Some((report, trace))
+}
+
+/// 戦闘の計算過程。
+#[derive(Debug)]
+pub struct Trace {
+    pub ally: TraceSide,
+    pub enemy: TraceSide,
+}
+
+/// 片方の陣営の計算過程。
+///
+/// 攻撃力はこの陣営が与えるもの、ダメージはこの陣営が受けるもの。
+#[derive(Debug)]
+pub struct TraceSide {
+    /// 健在な艦隊数 (敵の場合、駐留艦隊含む)。
+    pub fleet_count: usize,
+    /// 攻撃可能な総兵力 (clamp なし)。
+    pub attack_force: u32,
+    /// 攻撃可能な総兵力 (clamp 済み)。
+    pub attack_force_clamped: u32,
+    /// 修正前フォーメーション。
+    pub formation: Formation,
+    /// フォーメーション修正の判定値。`RuleSet::formation_ratio_forced()` 以下ならフォーメーション 0 になる。
+    pub formation_ratio: u32,
+    /// 修正後フォーメーション。
+    pub formation_modified: Formation,
+    /// フォーメーション修正によりフォーメーション 0 が強制されたかどうか。
+    pub formation_is_forced: bool,
+    /// フォーメーション係数。
+    pub attack_coef: u32,
+    /// 攻撃力 (能力補正前)。
+    pub attack_raw: u32,
+    /// この陣営の指揮官の能力のうち有効だったもの。
+    pub abilities_effective: Vec<Ability>,
+    /// 攻撃力 (能力補正済み)。
+    pub attack: u32,
+    /// 1 艦隊あたりのダメージ計算の分子 (相手の攻撃力 * 100)。
+    pub damage_numer: u64,
+    /// 1 艦隊あたりのダメージ計算の分母 (健在な艦隊数 * `RuleSet::damage_divisor()`)。
+    pub damage_denom: u64,
+    /// 1 艦隊あたりのダメージ (上限 100 適用前)。
+    pub damage_per_fleet_uncapped: u32,
+    /// 1 艦隊あたりのダメージ (上限 100 適用済み)。
+    pub damage_per_fleet: u32,
+    /// 各艦隊の被ダメージ過程 (駐留艦隊は含まない)。
+    pub fleets: Vec<TraceFleet>,
+    /// 駐留艦隊の被ダメージ過程。味方の場合は `None`。
+    pub guard: Option<TraceFleet>,
+}
+
+/// 1 個艦隊の被ダメージ過程。
+#[derive(Debug)]
+pub struct TraceFleet {
+    /// 戦闘前の兵力。
+    pub force: FleetForce,
+    /// ダメージを差し引いた値 (0 未満は 0)。
+    pub force_subtracted: u32,
+    /// 全滅判定のしきい値 (force_subtracted がこれ以下なら全滅)。
+    pub annihilation: u32,
+    /// 全滅判定に該当したかどうか。
+    pub is_annihilated: bool,
+    /// 戦闘後の兵力。
+    pub force_damaged: FleetForce,
+}
+
+impl TraceFleet {
+    fn new(force: FleetForce, damage_per_fleet: u32, annihilation: u32) -> Self {
+        let force_subtracted = force.inner().saturating_sub(damage_per_fleet);
+
+        Self {
+            force,
+            force_subtracted,
+            annihilation,
+            is_annihilated: force_subtracted <= annihilation,
+            force_damaged: damage_fleet(force, damage_per_fleet, annihilation),
+        }
+    }
+}
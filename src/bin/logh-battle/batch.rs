@@ -0,0 +1,216 @@
+//! CSV による一括シミュレーション。
+//!
+//! 入力 CSV の 1 行が 1 つの `Query` に対応する。列名は以下の通り (順序は問わない)。
+//!
+//! * `ally_force_1` .. `ally_force_11`: 味方の各艦隊の兵力 (省略時 0)
+//! * `ally_tired_1` .. `ally_tired_11`: 味方の各艦隊の疲労度が 80 以上かどうか (0/1, 省略時 0)
+//! * `ally_formation`: 味方のフォーメーション (省略時 1)
+//! * `ally_abilities`: 味方の指揮官の能力 (空白区切り, 省略時なし)
+//! * `enemy_guard_force`: 敵の駐留艦隊の兵力 (省略時 0)
+//! * `enemy_guard_tired`: 敵の駐留艦隊の疲労度が 80 以上かどうか (0/1, 省略時 0)
+//! * `enemy_force_1` .. `enemy_force_15`: 敵の各艦隊の兵力 (省略時 0)
+//! * `enemy_tired_1` .. `enemy_tired_15`: 敵の各艦隊の疲労度が 80 以上かどうか (0/1, 省略時 0)
+//! * `enemy_formation`: 敵のフォーメーション (省略時 1)
+//! * `enemy_abilities`: 敵の指揮官の能力 (空白区切り, 省略時なし)
+//! * `enemy_has_yang`: ヤン参戦 (0/1, 省略時 0)
+//!
+//! 出力 CSV の各行は先頭に入力の行番号 (`line`) とエラー (`error`) を持ち、以降は
+//! `output::csv_header()` の列が続く。不正な行 (列数が違う行、値を解釈できない行、戦闘を
+//! 計算できない行) でも処理は中断せず、`error` に理由を書いて残りの列を空にした行を出力する。
+//! 正常な行の `error` は空。
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use anyhow::{bail, ensure, Context as _};
+
+use logh_battle::battle::*;
+
+use crate::output;
+
+/// 入力 CSV の各行を rules でシミュレートし、結果を wtr に書き出す。不正な行の数を返す。
+///
+/// 入力の読み取り自体に失敗した場合 (I/O エラー) は中断してエラーを返す。
+pub fn run<R: Read, W: Write>(rdr: R, wtr: W, rules: &RuleSet) -> anyhow::Result<usize> {
+    // 列数の違う行も読み取り、行ごとのエラーとして扱う。
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    let columns = Columns::new(rdr.headers()?)?;
+
+    let mut header = vec!["line".to_owned(), "error".to_owned()];
+    header.extend(output::csv_header());
+    wtr.write_record(&header)?;
+
+    let mut error_count = 0;
+
+    for record in rdr.records() {
+        let (line, result) = match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |pos| pos.line());
+                (line, simulate_record(&columns, &record, rules))
+            }
+            Err(e) if e.is_io_error() => return Err(e.into()),
+            Err(e) => {
+                let line = e.position().map_or(0, |pos| pos.line());
+                (line, Err(anyhow::Error::new(e).context("malformed row")))
+            }
+        };
+
+        let mut row = vec![line.to_string()];
+        match result {
+            Ok(report) => {
+                row.push(String::new());
+                row.extend(output::csv_record(&report));
+            }
+            Err(e) => {
+                row.push(format!("{e:#}"));
+                row.extend(output::csv_header().iter().map(|_| String::new()));
+                error_count += 1;
+            }
+        }
+        wtr.write_record(&row)?;
+    }
+
+    wtr.flush()?;
+
+    Ok(error_count)
+}
+
+fn simulate_record(
+    columns: &Columns,
+    record: &csv::StringRecord,
+    rules: &RuleSet,
+) -> anyhow::Result<Report> {
+    ensure!(
+        record.len() == columns.len(),
+        "expected {} fields, found {}",
+        columns.len(),
+        record.len()
+    );
+
+    let query = columns.parse_query(record)?;
+    query.validate().context("invalid query")?;
+
+    Ok(battle_simulate(&query, rules).expect("query should be valid"))
+}
+
+/// 列名から列番号への対応。
+struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    fn new(header: &csv::StringRecord) -> anyhow::Result<Self> {
+        let known = known_columns();
+
+        let mut map = HashMap::new();
+        for (i, name) in header.iter().enumerate() {
+            let name = name.trim();
+            ensure!(known.iter().any(|e| e == name), "unknown column: {name}");
+            ensure!(
+                map.insert(name.to_owned(), i).is_none(),
+                "duplicate column: {name}"
+            );
+        }
+
+        Ok(Self(map))
+    }
+
+    /// 列数を返す。
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn get<'a>(&self, record: &'a csv::StringRecord, name: &str) -> Option<&'a str> {
+        self.0.get(name).and_then(|&i| record.get(i)).map(str::trim)
+    }
+
+    fn parse<T>(&self, record: &csv::StringRecord, name: &str, default: T) -> anyhow::Result<T>
+    where
+        T: std::str::FromStr,
+        T::Err: Into<anyhow::Error>,
+    {
+        match self.get(record, name) {
+            None | Some("") => Ok(default),
+            Some(s) => s
+                .parse()
+                .map_err(Into::into)
+                .with_context(|| format!("invalid {name}: {s}")),
+        }
+    }
+
+    fn parse_bool(&self, record: &csv::StringRecord, name: &str) -> anyhow::Result<bool> {
+        match self.get(record, name) {
+            None | Some("") | Some("0") => Ok(false),
+            Some("1") => Ok(true),
+            Some(s) => bail!("invalid {name}: {s} (should be 0 or 1)"),
+        }
+    }
+
+    fn parse_abilities(
+        &self,
+        record: &csv::StringRecord,
+        name: &str,
+    ) -> anyhow::Result<Vec<Ability>> {
+        self.get(record, name)
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|s| s.parse().with_context(|| format!("invalid {name}: {s}")))
+            .collect()
+    }
+
+    fn parse_query(&self, record: &csv::StringRecord) -> anyhow::Result<Query> {
+        let mut query = Query::default();
+
+        for i in 0..ALLY_FLEET_COUNT {
+            let force = self.parse(record, &format!("ally_force_{}", i + 1), FleetForce::zero())?;
+            query.set_ally_fleet_force(i, force);
+
+            let is_tired = self.parse_bool(record, &format!("ally_tired_{}", i + 1))?;
+            query.set_ally_fleet_is_tired(i, is_tired);
+        }
+        query.set_ally_formation(self.parse(record, "ally_formation", query.ally_formation())?);
+        query.set_ally_abilities(self.parse_abilities(record, "ally_abilities")?);
+
+        query.set_enemy_guard_force(self.parse(record, "enemy_guard_force", FleetForce::zero())?);
+        query.set_enemy_guard_is_tired(self.parse_bool(record, "enemy_guard_tired")?);
+        for i in 0..ENEMY_FLEET_COUNT {
+            let force = self.parse(
+                record,
+                &format!("enemy_force_{}", i + 1),
+                FleetForce::zero(),
+            )?;
+            query.set_enemy_fleet_force(i, force);
+
+            let is_tired = self.parse_bool(record, &format!("enemy_tired_{}", i + 1))?;
+            query.set_enemy_fleet_is_tired(i, is_tired);
+        }
+        query.set_enemy_formation(self.parse(
+            record,
+            "enemy_formation",
+            query.enemy_formation(),
+        )?);
+        query.set_enemy_abilities(self.parse_abilities(record, "enemy_abilities")?);
+        if self.parse_bool(record, "enemy_has_yang")? {
+            query.set_enemy_has_yang(true);
+        }
+
+        Ok(query)
+    }
+}
+
+fn known_columns() -> Vec<String> {
+    let mut columns = vec![];
+    columns.extend((1..=ALLY_FLEET_COUNT).map(|i| format!("ally_force_{i}")));
+    columns.extend((1..=ALLY_FLEET_COUNT).map(|i| format!("ally_tired_{i}")));
+    columns.push("ally_formation".to_owned());
+    columns.push("ally_abilities".to_owned());
+    columns.push("enemy_guard_force".to_owned());
+    columns.push("enemy_guard_tired".to_owned());
+    columns.extend((1..=ENEMY_FLEET_COUNT).map(|i| format!("enemy_force_{i}")));
+    columns.extend((1..=ENEMY_FLEET_COUNT).map(|i| format!("enemy_tired_{i}")));
+    columns.push("enemy_formation".to_owned());
+    columns.push("enemy_abilities".to_owned());
+    columns.push("enemy_has_yang".to_owned());
+
+    columns
+}
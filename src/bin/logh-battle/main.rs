@@ -0,0 +1,271 @@
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as _};
+use clap::Parser;
+
+use logh_battle::battle::*;
+
+mod batch;
+mod output;
+mod tables;
+
+use self::output::Format;
+use self::tables::TableKind;
+
+/// 銀河英雄伝説 (FC) の戦闘シミュレーター。
+///
+/// 入力は簡易記法・JSON ファイル・オプションのいずれかで与える。簡易記法や JSON と
+/// オプションを同時に与えた場合、オプションで指定した項目だけが上書きされる。
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// 簡易記法による入力 (例: "ally=100,100,80 f=3 tired=2; enemy=guard:100,50,50 f=1 yang")
+    notation: Option<String>,
+
+    /// JSON 形式の入力ファイル ("-" なら標準入力)
+    #[arg(long, conflicts_with = "notation")]
+    json: Option<PathBuf>,
+
+    /// 各行が 1 つの入力である CSV ファイル ("-" なら標準入力) を一括処理し、結果を CSV で出力する
+    /// (入力を上書きするオプションや --format とは併用できない)
+    #[arg(long, conflicts_with_all = [
+        "notation", "json", "format",
+        "ally", "ally_tired", "ally_formation", "ally_abilities",
+        "enemy", "enemy_tired", "guard", "guard_tired", "enemy_formation", "enemy_abilities", "yang",
+    ])]
+    batch: Option<PathBuf>,
+
+    /// 入力空間全体を列挙した表を出力する (入力を指定するオプションとは併用できない)
+    #[arg(long, value_enum, conflicts_with_all = [
+        "notation", "json", "batch",
+        "ally", "ally_tired", "ally_formation", "ally_abilities",
+        "enemy", "enemy_tired", "guard", "guard_tired", "enemy_formation", "enemy_abilities", "yang",
+    ])]
+    table: Option<TableKind>,
+
+    /// 入力空間全体の統計 (ダメージが上限に達する割合、フォーメーション 0 が強制される割合など) を出力する
+    /// (入力を指定するオプションとは併用できない)
+    #[arg(long, conflicts_with_all = [
+        "notation", "json", "batch", "table",
+        "ally", "ally_tired", "ally_formation", "ally_abilities",
+        "enemy", "enemy_tired", "guard", "guard_tired", "enemy_formation", "enemy_abilities", "yang",
+    ])]
+    stats: bool,
+
+    /// 戦闘計算の規則を書いた JSON ファイル (省略時は FC 版の規則)
+    #[arg(long)]
+    rules: Option<PathBuf>,
+
+    #[command(flatten)]
+    flags: QueryFlags,
+
+    /// 出力形式
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(Debug, clap::Args)]
+struct QueryFlags {
+    /// 味方の各艦隊の兵力 (カンマ区切り)
+    #[arg(long, value_delimiter = ',')]
+    ally: Option<Vec<FleetForce>>,
+
+    /// 疲労度 80 以上の味方艦隊の番号 (1 始まり、カンマ区切り)
+    #[arg(long, value_delimiter = ',')]
+    ally_tired: Option<Vec<usize>>,
+
+    /// 味方のフォーメーション
+    #[arg(long)]
+    ally_formation: Option<Formation>,
+
+    /// 味方の指揮官の能力 ("yang" または "攻撃加算:防御除数:防御加算[:無効フォーメーション]"、カンマ区切り)
+    #[arg(long, value_delimiter = ',')]
+    ally_abilities: Option<Vec<Ability>>,
+
+    /// 敵の各艦隊の兵力 (駐留艦隊を除く、カンマ区切り)
+    #[arg(long, value_delimiter = ',')]
+    enemy: Option<Vec<FleetForce>>,
+
+    /// 疲労度 80 以上の敵艦隊の番号 (1 始まり、カンマ区切り)
+    #[arg(long, value_delimiter = ',')]
+    enemy_tired: Option<Vec<usize>>,
+
+    /// 敵の駐留艦隊の兵力
+    #[arg(long)]
+    guard: Option<FleetForce>,
+
+    /// 敵の駐留艦隊の疲労度が 80 以上
+    #[arg(long)]
+    guard_tired: bool,
+
+    /// 敵のフォーメーション
+    #[arg(long)]
+    enemy_formation: Option<Formation>,
+
+    /// 敵の指揮官の能力 (書式は --ally-abilities と同じ)
+    #[arg(long, value_delimiter = ',')]
+    enemy_abilities: Option<Vec<Ability>>,
+
+    /// ヤン参戦
+    #[arg(long)]
+    yang: bool,
+}
+
+impl QueryFlags {
+    /// 指定された項目で query を上書きする。
+    fn apply(&self, query: &mut Query) -> anyhow::Result<()> {
+        if let Some(forces) = &self.ally {
+            ensure!(
+                forces.len() <= ALLY_FLEET_COUNT,
+                "too many ally fleets: {} (max {ALLY_FLEET_COUNT})",
+                forces.len()
+            );
+            for i in 0..ALLY_FLEET_COUNT {
+                let force = forces.get(i).copied().unwrap_or_else(FleetForce::zero);
+                query.set_ally_fleet_force(i, force);
+            }
+        }
+
+        if let Some(tireds) = &self.ally_tired {
+            for i in 0..ALLY_FLEET_COUNT {
+                query.set_ally_fleet_is_tired(i, false);
+            }
+            for &i in tireds {
+                ensure!(
+                    (1..=ALLY_FLEET_COUNT).contains(&i),
+                    "ally fleet number is out of range: {i}"
+                );
+                query.set_ally_fleet_is_tired(i - 1, true);
+            }
+        }
+
+        if let Some(formation) = self.ally_formation {
+            query.set_ally_formation(formation);
+        }
+
+        if let Some(abilities) = &self.ally_abilities {
+            query.set_ally_abilities(abilities.clone());
+        }
+
+        if let Some(forces) = &self.enemy {
+            ensure!(
+                forces.len() <= ENEMY_FLEET_COUNT,
+                "too many enemy fleets: {} (max {ENEMY_FLEET_COUNT})",
+                forces.len()
+            );
+            for i in 0..ENEMY_FLEET_COUNT {
+                let force = forces.get(i).copied().unwrap_or_else(FleetForce::zero);
+                query.set_enemy_fleet_force(i, force);
+            }
+        }
+
+        if let Some(tireds) = &self.enemy_tired {
+            for i in 0..ENEMY_FLEET_COUNT {
+                query.set_enemy_fleet_is_tired(i, false);
+            }
+            for &i in tireds {
+                ensure!(
+                    (1..=ENEMY_FLEET_COUNT).contains(&i),
+                    "enemy fleet number is out of range: {i}"
+                );
+                query.set_enemy_fleet_is_tired(i - 1, true);
+            }
+        }
+
+        if let Some(force) = self.guard {
+            query.set_enemy_guard_force(force);
+        }
+
+        if self.guard_tired {
+            query.set_enemy_guard_is_tired(true);
+        }
+
+        if let Some(formation) = self.enemy_formation {
+            query.set_enemy_formation(formation);
+        }
+
+        if let Some(abilities) = &self.enemy_abilities {
+            query.set_enemy_abilities(abilities.clone());
+        }
+
+        if self.yang {
+            query.set_enemy_has_yang(true);
+        }
+
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let rules = match &cli.rules {
+        Some(path) => read_rules(path)?,
+        None => RuleSet::default(),
+    };
+
+    if let Some(path) = &cli.batch {
+        return run_batch(path, &rules);
+    }
+
+    if let Some(kind) = cli.table {
+        let mut stdout = std::io::stdout().lock();
+        return tables::write_table(&mut stdout, cli.format, kind, &rules);
+    }
+
+    if cli.stats {
+        let mut stdout = std::io::stdout().lock();
+        return tables::write_stats(&mut stdout, cli.format, &rules);
+    }
+
+    let mut query = if let Some(notation) = &cli.notation {
+        notation.parse().context("invalid notation")?
+    } else if let Some(path) = &cli.json {
+        read_json(path)?
+    } else {
+        Query::default()
+    };
+    cli.flags.apply(&mut query)?;
+
+    query.validate().context("invalid query")?;
+    let report = battle_simulate(&query, &rules).expect("query should be valid");
+
+    let mut stdout = std::io::stdout().lock();
+    output::write_report(&mut stdout, cli.format, &report)?;
+
+    Ok(())
+}
+
+fn run_batch(path: &Path, rules: &RuleSet) -> anyhow::Result<()> {
+    let input = read_input(path)?;
+    let stdout = std::io::stdout().lock();
+
+    let error_count = batch::run(input.as_bytes(), stdout, rules)?;
+    ensure!(error_count == 0, "{error_count} invalid row(s)");
+
+    Ok(())
+}
+
+fn read_json(path: &Path) -> anyhow::Result<Query> {
+    let input = read_input(path)?;
+
+    serde_json::from_str(&input).context("invalid JSON")
+}
+
+fn read_rules(path: &Path) -> anyhow::Result<RuleSet> {
+    let input = read_input(path)?;
+
+    serde_json::from_str(&input).context("invalid rules")
+}
+
+/// ファイル (path が "-" なら標準入力) の内容を読み取る。
+fn read_input(path: &Path) -> anyhow::Result<String> {
+    if path == Path::new("-") {
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        Ok(buf)
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::Write;
+
+use logh_battle::battle::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// 人間向けの表
+    Table,
+    /// JSON
+    Json,
+    /// CSV (ヘッダ行 + データ行)
+    Csv,
+}
+
+pub fn write_report<W: Write>(wtr: &mut W, format: Format, report: &Report) -> anyhow::Result<()> {
+    match format {
+        Format::Table => write_table(wtr, report)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *wtr, report)?;
+            writeln!(wtr)?;
+        }
+        Format::Csv => {
+            let mut wtr = csv::Writer::from_writer(wtr);
+            wtr.write_record(csv_header())?;
+            wtr.write_record(csv_record(report))?;
+            wtr.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_table<W: Write>(wtr: &mut W, report: &Report) -> anyhow::Result<()> {
+    let header_fleets: String = (1..=ENEMY_FLEET_COUNT).map(|i| format!("{i:>4}")).collect();
+    writeln!(
+        wtr,
+        "{:<6}{:>10}{:>8}{:>6}{header_fleets}",
+        "", "formation", "damage", "guard"
+    )?;
+
+    let ally_fleets: String = (0..ALLY_FLEET_COUNT)
+        .map(|i| format!("{:>4}", report.ally_fleet_force(i)))
+        .collect();
+    writeln!(
+        wtr,
+        "{:<6}{:>10}{:>8}{:>6}{ally_fleets}",
+        "ally",
+        report.ally_formation(),
+        report.ally_damage_per_fleet(),
+        "-",
+    )?;
+
+    let enemy_fleets: String = (0..ENEMY_FLEET_COUNT)
+        .map(|i| format!("{:>4}", report.enemy_fleet_force(i)))
+        .collect();
+    writeln!(
+        wtr,
+        "{:<6}{:>10}{:>8}{:>6}{enemy_fleets}",
+        "enemy",
+        report.enemy_formation(),
+        report.enemy_damage_per_fleet(),
+        report.enemy_guard_force(),
+    )?;
+
+    Ok(())
+}
+
+/// `Report` の CSV 表現の列名を返す。
+pub fn csv_header() -> Vec<String> {
+    let mut header = vec![
+        "ally_formation".to_owned(),
+        "ally_damage_per_fleet".to_owned(),
+    ];
+    header.extend((1..=ALLY_FLEET_COUNT).map(|i| format!("ally_force_{i}")));
+    header.extend([
+        "enemy_formation".to_owned(),
+        "enemy_damage_per_fleet".to_owned(),
+        "enemy_guard_force".to_owned(),
+    ]);
+    header.extend((1..=ENEMY_FLEET_COUNT).map(|i| format!("enemy_force_{i}")));
+
+    header
+}
+
+/// `Report` の CSV 表現を返す。列の並びは `csv_header()` と同じ。
+pub fn csv_record(report: &Report) -> Vec<String> {
+    let mut record = vec![
+        report.ally_formation().to_string(),
+        report.ally_damage_per_fleet().to_string(),
+    ];
+    record.extend((0..ALLY_FLEET_COUNT).map(|i| report.ally_fleet_force(i).to_string()));
+    record.extend([
+        report.enemy_formation().to_string(),
+        report.enemy_damage_per_fleet().to_string(),
+        report.enemy_guard_force().to_string(),
+    ]);
+    record.extend((0..ENEMY_FLEET_COUNT).map(|i| report.enemy_fleet_force(i).to_string()));
+
+    record
+}
@@ -0,0 +1,190 @@
+//! 入力空間全体を列挙した表と統計の出力。
+
+use std::io::Write;
+
+use logh_battle::battle::*;
+
+use crate::output::Format;
+
+/// 出力する表の種類。
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum TableKind {
+    /// 1 艦隊あたりのダメージ
+    Damage,
+    /// フォーメーション修正 (フォーメーション 0 の強制)
+    Collapse,
+}
+
+pub fn write_table<W: Write>(
+    wtr: &mut W,
+    format: Format,
+    kind: TableKind,
+    rules: &RuleSet,
+) -> anyhow::Result<()> {
+    match kind {
+        TableKind::Damage => {
+            let entries = damage_table(rules);
+            if format == Format::Json {
+                return write_json(wtr, &entries);
+            }
+
+            let header = [
+                "attack_force",
+                "attacker_formation",
+                "defender_formation",
+                "defender_fleet_count",
+                "yang",
+                "attack",
+                "damage_per_fleet",
+            ];
+            let records = entries.iter().map(|entry| {
+                vec![
+                    entry.attack_force().to_string(),
+                    entry.attacker_formation().to_string(),
+                    entry.defender_formation().to_string(),
+                    entry.defender_fleet_count().to_string(),
+                    entry.yang().to_string(),
+                    entry.attack().to_string(),
+                    entry.damage_per_fleet().to_string(),
+                ]
+            });
+            write_records(wtr, format, &header, records)
+        }
+
+        TableKind::Collapse => {
+            let entries = collapse_table(rules);
+            if format == Format::Json {
+                return write_json(wtr, &entries);
+            }
+
+            let header = [
+                "fleet_count",
+                "attack_force_count",
+                "collapsed_count",
+                "attack_force_min_uncollapsed",
+            ];
+            let records = entries.iter().map(|entry| {
+                vec![
+                    entry.fleet_count().to_string(),
+                    entry.attack_force_count().to_string(),
+                    entry.collapsed_count().to_string(),
+                    entry
+                        .attack_force_min_uncollapsed()
+                        .map_or_else(String::new, |x| x.to_string()),
+                ]
+            });
+            write_records(wtr, format, &header, records)
+        }
+    }
+}
+
+pub fn write_stats<W: Write>(wtr: &mut W, format: Format, rules: &RuleSet) -> anyhow::Result<()> {
+    let stats = TableStats::new(&damage_table(rules), &collapse_table(rules));
+
+    let rows = [
+        ("damage_entry_count", stats.damage_entry_count().to_string()),
+        (
+            "damage_capped_fraction",
+            format!("{:.4}", stats.damage_capped_fraction()),
+        ),
+        (
+            "damage_zero_fraction",
+            format!("{:.4}", stats.damage_zero_fraction()),
+        ),
+        (
+            "collapse_config_count",
+            stats.collapse_config_count().to_string(),
+        ),
+        (
+            "collapsed_fraction",
+            format!("{:.4}", stats.collapsed_fraction()),
+        ),
+    ];
+
+    match format {
+        Format::Table => {
+            for (name, value) in rows {
+                writeln!(wtr, "{name:<24}{value:>10}")?;
+            }
+        }
+        Format::Json => write_json(wtr, &stats)?,
+        Format::Csv => {
+            let mut wtr = csv::Writer::from_writer(wtr);
+            wtr.write_record(rows.iter().map(|(name, _)| name))?;
+            wtr.write_record(rows.iter().map(|(_, value)| value))?;
+            wtr.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_json<W: Write, T: serde::Serialize + ?Sized>(
+    wtr: &mut W,
+    value: &T,
+) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(&mut *wtr, value)?;
+    writeln!(wtr)?;
+
+    Ok(())
+}
+
+/// 表を CSV または人間向けの表 (右揃え) で書き出す。
+fn write_records<W, I>(
+    wtr: &mut W,
+    format: Format,
+    header: &[&str],
+    records: I,
+) -> anyhow::Result<()>
+where
+    W: Write,
+    I: Iterator<Item = Vec<String>>,
+{
+    match format {
+        Format::Csv => {
+            let mut wtr = csv::Writer::from_writer(wtr);
+            wtr.write_record(header)?;
+            for record in records {
+                wtr.write_record(&record)?;
+            }
+            wtr.flush()?;
+        }
+        Format::Table => {
+            // 列幅は見出しと全ての値の最大幅に、列の間の空白 2 文字を加えたもの。
+            let records: Vec<_> = records.collect();
+            let widths: Vec<_> = header
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let value_width = records
+                        .iter()
+                        .filter_map(|record| record.get(i))
+                        .map(|value| value.chars().count())
+                        .max()
+                        .unwrap_or(0);
+
+                    name.chars().count().max(value_width) + 2
+                })
+                .collect();
+
+            let line: String = header
+                .iter()
+                .zip(&widths)
+                .map(|(name, &width)| format!("{name:>width$}"))
+                .collect();
+            writeln!(wtr, "{line}")?;
+
+            for record in records {
+                let line: String = record
+                    .iter()
+                    .zip(&widths)
+                    .map(|(value, &width)| format!("{value:>width$}"))
+                    .collect();
+                writeln!(wtr, "{line}")?;
+            }
+        }
+        Format::Json => unreachable!("JSON is written by write_json()"),
+    }
+
+    Ok(())
+}
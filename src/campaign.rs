@@ -0,0 +1,301 @@
+//! 疲労度の推移を考慮した連続戦闘 (複数回の戦闘)。
+//!
+//! 味方の各艦隊は数値の疲労度を持つ。疲労度は戦闘のラウンドごとに上がり、戦闘の間の
+//! ターン経過で回復する。各ラウンドの前に疲労度から `Query::set_ally_fleet_is_tired()` の
+//! フラグを導出するので、疲労度が `Fatigue::TIRED` 以上の艦隊は攻撃に参加しなくなる。
+//!
+//! 各戦闘の敵陣営は query のものを使う (戦闘ごとに新しい敵と戦う)。味方の兵力は引き継ぐ。
+
+use anyhow::Context as _;
+
+use crate::battle::*;
+
+/// 味方の各艦隊の疲労度。
+pub type AllyFatigues = [Fatigue; ALLY_FLEET_COUNT];
+
+/// 疲労度の推移を考慮して battle_count 回の戦闘を行う。
+///
+/// 各戦闘はいずれかの陣営が全滅するか、ラウンド数が round_max に達するまで続く。
+/// 戦闘の間には rule の `rest_turns()` ターンが経過する。味方が全滅したらそこで終了する。
+///
+/// query が不正な場合は `None` を返す。
+pub fn campaign_simulate(
+    query: &Query,
+    rules: &RuleSet,
+    fatigues: &AllyFatigues,
+    rule: &FatigueRule,
+    battle_count: usize,
+    round_max: usize,
+) -> Option<Campaign> {
+    if !query.is_valid() {
+        return None;
+    }
+
+    let mut query_cur = query.clone();
+    let mut fatigues = *fatigues;
+    let mut battles = Vec::<CampaignBattle>::new();
+
+    for _ in 0..battle_count {
+        if !battles.is_empty() {
+            for fatigue in &mut fatigues {
+                *fatigue = fatigue.recover(rule.rest_turns, rule.recovery_per_turn);
+            }
+            reset_enemy_forces(&mut query_cur, query);
+        }
+
+        let battle =
+            campaign_simulate_battle(&mut query_cur, rules, &mut fatigues, rule, round_max);
+        let ally_is_annihilated = matches!(
+            battle.end,
+            RoundsEnd::AllyAnnihilated | RoundsEnd::BothAnnihilated
+        );
+
+        battles.push(battle);
+
+        if ally_is_annihilated {
+            break;
+        }
+    }
+
+    Some(Campaign { battles })
+}
+
+/// 1 回の戦闘を行い、query と fatigues を戦闘後の状態に更新する。
+fn campaign_simulate_battle(
+    query: &mut Query,
+    rules: &RuleSet,
+    fatigues: &mut AllyFatigues,
+    rule: &FatigueRule,
+    round_max: usize,
+) -> CampaignBattle {
+    let mut fatigues_history = Vec::<AllyFatigues>::new();
+
+    let prepare = |query: &mut Query| {
+        fatigues_history.push(*fatigues);
+
+        for (i, fatigue) in fatigues.iter_mut().enumerate() {
+            query.set_ally_fleet_is_tired(i, fatigue.is_tired());
+
+            // 戦闘に参加した艦隊 (ラウンド開始時に健在だった艦隊) の疲労度が上がる。
+            if !query.ally_fleet_force(i).is_zero() {
+                *fatigue = fatigue.gain(rule.gain_per_round);
+            }
+        }
+    };
+
+    let (rounds, end) = battle_simulate_rounds_with(query, rules, round_max, prepare, |_, _| false);
+
+    let rounds = rounds
+        .into_iter()
+        .zip(fatigues_history)
+        .map(|((query, report), fatigues)| CampaignRound {
+            query,
+            fatigues,
+            report,
+        })
+        .collect();
+
+    CampaignBattle { rounds, end }
+}
+
+/// query の敵陣営の兵力を original のものに戻す。
+fn reset_enemy_forces(query: &mut Query, original: &Query) {
+    for i in 0..ENEMY_FLEET_COUNT {
+        query.set_enemy_fleet_force(i, original.enemy_fleet_force(i));
+    }
+    query.set_enemy_guard_force(original.enemy_guard_force());
+}
+
+/// `campaign_simulate()` の結果。
+#[derive(Debug)]
+pub struct Campaign {
+    battles: Vec<CampaignBattle>,
+}
+
+impl Campaign {
+    /// 各戦闘の結果を返す。味方が全滅した場合、指定した回数より少ないことがある。
+    pub fn battles(&self) -> &[CampaignBattle] {
+        &self.battles
+    }
+
+    /// 味方の艦隊 idx が初めて疲労により攻撃に参加しなくなったラウンドを
+    /// (戦闘のインデックス, ラウンドのインデックス) で返す。
+    ///
+    /// 健在なまま疲労しなかった艦隊、および疲労する前に全滅した艦隊については `None` を返す。
+    pub fn ally_fleet_dropout(&self, idx: usize) -> Option<(usize, usize)> {
+        self.battles
+            .iter()
+            .enumerate()
+            .find_map(|(i_battle, battle)| {
+                battle
+                    .rounds
+                    .iter()
+                    .position(|round| {
+                        !round.query.ally_fleet_force(idx).is_zero()
+                            && round.ally_fleet_is_tired(idx)
+                    })
+                    .map(|i_round| (i_battle, i_round))
+            })
+    }
+}
+
+/// `Campaign` 内の 1 回の戦闘。
+#[derive(Debug)]
+pub struct CampaignBattle {
+    rounds: Vec<CampaignRound>,
+    end: RoundsEnd,
+}
+
+impl CampaignBattle {
+    /// 各ラウンドを返す。
+    pub fn rounds(&self) -> &[CampaignRound] {
+        &self.rounds
+    }
+
+    /// 戦闘が終了した理由を返す。
+    pub fn end(&self) -> RoundsEnd {
+        self.end
+    }
+}
+
+/// `CampaignBattle` 内の 1 ラウンド。
+#[derive(Debug)]
+pub struct CampaignRound {
+    query: Query,
+    fatigues: AllyFatigues,
+    report: Report,
+}
+
+impl CampaignRound {
+    /// ラウンド開始時の入力 (疲労フラグは疲労度から導出済み) を返す。
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// ラウンド開始時の味方の艦隊 idx の疲労度を返す。
+    pub fn ally_fleet_fatigue(&self, idx: usize) -> Fatigue {
+        self.fatigues[idx]
+    }
+
+    /// ラウンド開始時に味方の艦隊 idx が疲労により攻撃に参加しないかどうかを返す。
+    pub fn ally_fleet_is_tired(&self, idx: usize) -> bool {
+        self.query.ally_fleet_is_tired(idx)
+    }
+
+    /// ラウンドの戦闘結果を返す。
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+}
+
+/// 疲労度の増減の規則。
+///
+/// 実機の値は未確認なので、デフォルト値は目安にすぎない。値の範囲は制限しない。疲労度は
+/// `Fatigue::MIN` から `Fatigue::MAX` の範囲に飽和するので、大きな値は上限・下限への到達を意味する。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FatigueRule {
+    gain_per_round: u32,
+    recovery_per_turn: u32,
+    rest_turns: u32,
+}
+
+impl FatigueRule {
+    pub fn new(gain_per_round: u32, recovery_per_turn: u32, rest_turns: u32) -> Self {
+        Self {
+            gain_per_round,
+            recovery_per_turn,
+            rest_turns,
+        }
+    }
+
+    /// 1 ラウンドごとの疲労度の上昇量を返す。
+    pub fn gain_per_round(&self) -> u32 {
+        self.gain_per_round
+    }
+
+    /// 1 ターンごとの疲労度の回復量を返す。
+    pub fn recovery_per_turn(&self) -> u32 {
+        self.recovery_per_turn
+    }
+
+    /// 戦闘の間に経過するターン数を返す。
+    pub fn rest_turns(&self) -> u32 {
+        self.rest_turns
+    }
+}
+
+impl Default for FatigueRule {
+    fn default() -> Self {
+        Self::new(20, 10, 1)
+    }
+}
+
+/// 1 個艦隊の疲労度。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Fatigue(u32);
+
+impl Fatigue {
+    pub const MIN: Self = Self(0);
+    pub const MAX: Self = Self(100);
+
+    /// これ以上なら疲労により攻撃に参加しない。
+    pub const TIRED: Self = Self(80);
+
+    pub fn new(inner: u32) -> Option<Self> {
+        (Self::MIN.0..=Self::MAX.0)
+            .contains(&inner)
+            .then_some(Self(inner))
+    }
+
+    pub const fn zero() -> Self {
+        Self(0)
+    }
+
+    pub const fn inner(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_tired(self) -> bool {
+        self >= Self::TIRED
+    }
+
+    /// 疲労度を amount だけ上げる (上限は `MAX`)。
+    fn gain(self, amount: u32) -> Self {
+        Self(self.0.saturating_add(amount).min(Self::MAX.0))
+    }
+
+    /// turns ターン分 (1 ターンあたり per_turn) 回復させる (下限は `MIN`)。
+    fn recover(self, turns: u32, per_turn: u32) -> Self {
+        Self(self.0.saturating_sub(turns.saturating_mul(per_turn)))
+    }
+}
+
+impl TryFrom<u32> for Fatigue {
+    type Error = anyhow::Error;
+
+    fn try_from(inner: u32) -> Result<Self, Self::Error> {
+        Self::new(inner).with_context(|| format!("fatigue value is out of range: {inner}"))
+    }
+}
+
+impl From<Fatigue> for u32 {
+    fn from(fatigue: Fatigue) -> Self {
+        fatigue.0
+    }
+}
+
+impl std::str::FromStr for Fatigue {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner: u32 = s.parse()?;
+
+        Self::try_from(inner)
+    }
+}
+
+impl std::fmt::Display for Fatigue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -1,5 +1,17 @@
-mod battle;
+//! 銀河英雄伝説 (FC) の戦闘シミュレーター。
+//!
+//! 戦闘計算は `battle`、それを使った解析は `allocate`, `campaign`, `recommend`, `sensitivity`, `solve` にある。
+//! wasm フロントエンド (`web` feature) はこれらを使う側の 1 つにすぎない。
+
+pub mod allocate;
+pub mod battle;
+pub mod campaign;
+pub mod recommend;
+pub mod sensitivity;
+pub mod solve;
 mod util;
+#[cfg(feature = "web")]
 mod web;
 
+#[cfg(feature = "web")]
 pub use self::web::start;
@@ -0,0 +1,318 @@
+use crate::battle::*;
+use crate::util;
+
+/// 味方の全フォーメーションについて戦闘結果を求め、objective の良い順に並べて返す。
+///
+/// フォーメーション以外の入力は query のものを使う。能力補正およびフォーメーション修正は
+/// 各フォーメーションの戦闘結果にそのまま反映される。評価が同じ場合はフォーメーションの昇順。
+///
+/// query が不正な場合は `None` を返す。
+pub fn recommend_formations(
+    query: &Query,
+    rules: &RuleSet,
+    objective: Objective,
+) -> Option<Vec<Outcome>> {
+    let mut outcomes = Formation::all()
+        .map(|formation| {
+            let mut query = query.clone();
+            query.set_ally_formation(formation);
+            Outcome::new(&query, rules)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    outcomes.sort_by_key(|e| std::cmp::Reverse(e.score(objective)));
+
+    Some(outcomes)
+}
+
+/// 味方と敵の全フォーメーションの組み合わせについて戦闘結果を求め、味方フォーメーションごとに
+/// 最悪・最良・期待値を集計する。
+///
+/// enemy_probs は敵の各フォーメーションの出現確率 (の比)。`None` なら一様とみなす。
+/// 最悪・最良は出現確率が正の敵フォーメーションだけから選ぶ。
+///
+/// query が不正な場合、enemy_probs に負の値や有限でない値が含まれる場合、または enemy_probs の
+/// 和が正でない場合は `None` を返す。
+pub fn analyze_formations(
+    query: &Query,
+    rules: &RuleSet,
+    objective: Objective,
+    enemy_probs: Option<&[f64; Formation::COUNT]>,
+) -> Option<FormationMatrix> {
+    let enemy_probs = enemy_probs.copied().unwrap_or([1.0; Formation::COUNT]);
+    if !enemy_probs.iter().all(|&p| p.is_finite() && p >= 0.0) {
+        return None;
+    }
+    let prob_sum: f64 = enemy_probs.iter().sum();
+    if !(prob_sum > 0.0 && prob_sum.is_finite()) {
+        return None;
+    }
+
+    let outcomes = Formation::all()
+        .map(|ally_formation| {
+            Formation::all()
+                .map(|enemy_formation| {
+                    let mut query = query.clone();
+                    query.set_ally_formation(ally_formation);
+                    query.set_enemy_formation(enemy_formation);
+                    Outcome::new(&query, rules)
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let summaries = outcomes
+        .iter()
+        .map(|row| {
+            // 出現しない敵フォーメーションは最悪・最良の候補から除く。確率の和は正なので空にならない。
+            let candidates = || {
+                row.iter()
+                    .zip(enemy_probs)
+                    .filter(|&(_, prob)| prob > 0.0)
+                    .map(|(outcome, _)| outcome)
+            };
+            let worst = candidates()
+                .min_by_key(|e| e.score(objective))
+                .expect("some enemy formation should have positive probability");
+            let best = candidates()
+                .max_by_key(|e| e.score(objective))
+                .expect("some enemy formation should have positive probability");
+            let expected = row
+                .iter()
+                .zip(enemy_probs)
+                .map(|(outcome, prob)| prob * outcome.value(objective) as f64)
+                .sum::<f64>()
+                / prob_sum;
+
+            FormationSummary {
+                worst_enemy_formation: worst.enemy_formation(),
+                best_enemy_formation: best.enemy_formation(),
+                worst: worst.value(objective),
+                best: best.value(objective),
+                expected,
+            }
+        })
+        .collect();
+
+    Some(FormationMatrix {
+        objective,
+        outcomes,
+        summaries,
+    })
+}
+
+/// フォーメーションの評価基準。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Objective {
+    /// 敵の損害 (総兵力の減少量) が大きいほど良い。
+    #[default]
+    EnemyDamage,
+    /// 味方の損害 (総兵力の減少量) が小さいほど良い。
+    AllyLoss,
+    /// 敵の損害から味方の損害を引いた値が大きいほど良い。
+    NetDamage,
+    /// 撃破した敵艦隊数 (駐留艦隊含む) が多いほど良い。
+    FleetsDestroyed,
+}
+
+impl Objective {
+    pub const ALL: [Self; 4] = [
+        Self::EnemyDamage,
+        Self::AllyLoss,
+        Self::NetDamage,
+        Self::FleetsDestroyed,
+    ];
+
+    /// 値が小さいほど良い評価基準かどうかを返す。
+    pub fn is_minimized(self) -> bool {
+        matches!(self, Self::AllyLoss)
+    }
+}
+
+/// 味方と敵のフォーメーションの組み合わせ 1 つに対する戦闘結果とその評価値。
+#[derive(Debug)]
+pub struct Outcome {
+    ally_formation: Formation,
+    enemy_formation: Formation,
+    report: Report,
+    ability_is_effective: bool,
+    enemy_damage: u32,
+    ally_loss: u32,
+    fleets_destroyed: usize,
+}
+
+impl Outcome {
+    pub(crate) fn new(query: &Query, rules: &RuleSet) -> Option<Self> {
+        let (report, trace) = battle_simulate_traced(query, rules)?;
+
+        Some(Self {
+            ally_formation: query.ally_formation(),
+            enemy_formation: query.enemy_formation(),
+            ability_is_effective: !trace.ally.abilities_effective.is_empty()
+                || !trace.enemy.abilities_effective.is_empty(),
+            enemy_damage: query.enemy_force_total() - report.enemy_force_total(),
+            ally_loss: query.ally_force_total() - report.ally_force_total(),
+            fleets_destroyed: query.enemy_fleet_count() - report.enemy_fleet_count(),
+            report,
+        })
+    }
+
+    /// objective に関する値を返す。
+    pub fn value(&self, objective: Objective) -> i64 {
+        match objective {
+            Objective::EnemyDamage => i64::from(self.enemy_damage),
+            Objective::AllyLoss => i64::from(self.ally_loss),
+            Objective::NetDamage => i64::from(self.enemy_damage) - i64::from(self.ally_loss),
+            Objective::FleetsDestroyed => i64::from(util::u32_from_usize(self.fleets_destroyed)),
+        }
+    }
+
+    /// objective に関する評価値 (大きいほど良い) を返す。
+    fn score(&self, objective: Objective) -> i64 {
+        let value = self.value(objective);
+
+        if objective.is_minimized() {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// 選択した (修正前の) 味方フォーメーションを返す。
+    pub fn ally_formation(&self) -> Formation {
+        self.ally_formation
+    }
+
+    /// 選択した (修正前の) 敵フォーメーションを返す。
+    pub fn enemy_formation(&self) -> Formation {
+        self.enemy_formation
+    }
+
+    /// 戦闘結果を返す。修正後フォーメーションはここから得られる。
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
+    /// いずれかの陣営で能力補正が適用されたかどうかを返す。
+    pub fn ability_is_effective(&self) -> bool {
+        self.ability_is_effective
+    }
+
+    /// 敵の損害を返す。
+    pub fn enemy_damage(&self) -> u32 {
+        self.enemy_damage
+    }
+
+    /// 味方の損害を返す。
+    pub fn ally_loss(&self) -> u32 {
+        self.ally_loss
+    }
+
+    /// 敵の損害から味方の損害を引いた値を返す。
+    pub fn net_damage(&self) -> i64 {
+        self.value(Objective::NetDamage)
+    }
+
+    /// 撃破した敵艦隊数 (駐留艦隊含む) を返す。
+    pub fn fleets_destroyed(&self) -> usize {
+        self.fleets_destroyed
+    }
+}
+
+/// 味方と敵の全フォーメーションの組み合わせに対する戦闘結果。
+#[derive(Debug)]
+pub struct FormationMatrix {
+    objective: Objective,
+    outcomes: Vec<Vec<Outcome>>,
+    summaries: Vec<FormationSummary>,
+}
+
+impl FormationMatrix {
+    /// 集計に使った評価基準を返す。
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    pub fn outcome(&self, ally_formation: Formation, enemy_formation: Formation) -> &Outcome {
+        &self.outcomes[ally_formation.index()][enemy_formation.index()]
+    }
+
+    /// 味方フォーメーションごとの集計結果を返す。
+    pub fn summary(&self, ally_formation: Formation) -> &FormationSummary {
+        &self.summaries[ally_formation.index()]
+    }
+
+    /// 最悪の場合の評価が最も良い味方フォーメーション (ミニマックス解) を返す。
+    ///
+    /// 同点の場合はフォーメーションの昇順で最初のもの。
+    pub fn minimax_formation(&self) -> Formation {
+        self.best_formation_by(|summary| summary.worst as f64)
+    }
+
+    /// 期待値が最も良い味方フォーメーションを返す。
+    ///
+    /// 同点の場合はフォーメーションの昇順で最初のもの。
+    pub fn expected_formation(&self) -> Formation {
+        self.best_formation_by(|summary| summary.expected)
+    }
+
+    fn best_formation_by<F>(&self, f: F) -> Formation
+    where
+        F: Fn(&FormationSummary) -> f64,
+    {
+        let sign = if self.objective.is_minimized() {
+            -1.0
+        } else {
+            1.0
+        };
+
+        Formation::all()
+            .reduce(|acc, formation| {
+                let score_acc = sign * f(self.summary(acc));
+                let score = sign * f(self.summary(formation));
+                if score > score_acc {
+                    formation
+                } else {
+                    acc
+                }
+            })
+            .expect("formations should not be empty")
+    }
+}
+
+/// 1 つの味方フォーメーションについて、敵フォーメーションを変えたときの評価値の集計。
+#[derive(Debug)]
+pub struct FormationSummary {
+    worst_enemy_formation: Formation,
+    best_enemy_formation: Formation,
+    worst: i64,
+    best: i64,
+    expected: f64,
+}
+
+impl FormationSummary {
+    /// 味方にとって最悪となる敵フォーメーションを返す。
+    pub fn worst_enemy_formation(&self) -> Formation {
+        self.worst_enemy_formation
+    }
+
+    /// 味方にとって最良となる敵フォーメーションを返す。
+    pub fn best_enemy_formation(&self) -> Formation {
+        self.best_enemy_formation
+    }
+
+    /// 最悪の場合の値を返す。
+    pub fn worst(&self) -> i64 {
+        self.worst
+    }
+
+    /// 最良の場合の値を返す。
+    pub fn best(&self) -> i64 {
+        self.best
+    }
+
+    /// 敵フォーメーションの出現確率で重み付けした期待値を返す。
+    pub fn expected(&self) -> f64 {
+        self.expected
+    }
+}
@@ -0,0 +1,150 @@
+//! 1 個艦隊の兵力を変化させたときの戦闘結果の変化 (感度分析)。
+//!
+//! 攻撃力とダメージの計算は整数演算なので (`attack_force / 100 * coef` など)、戦闘結果は
+//! 兵力に対して階段状に変化する。ここでは兵力を `FleetForce::MIN` から `FleetForce::MAX` まで
+//! 動かし、1 艦隊あたりのダメージが変わる兵力 (ブレークポイント) を求める。
+
+use crate::battle::*;
+use crate::recommend::Outcome;
+
+/// 兵力を変化させる艦隊。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SweepTarget {
+    AllyFleet(usize),
+    EnemyGuard,
+    EnemyFleet(usize),
+}
+
+impl SweepTarget {
+    /// 選べる全ての艦隊を返す。
+    pub fn all() -> impl Iterator<Item = Self> {
+        let ally = (0..ALLY_FLEET_COUNT).map(Self::AllyFleet);
+        let enemy = (0..ENEMY_FLEET_COUNT).map(Self::EnemyFleet);
+
+        ally.chain(std::iter::once(Self::EnemyGuard)).chain(enemy)
+    }
+
+    /// query における対象艦隊の兵力を返す。
+    pub fn force(self, query: &Query) -> FleetForce {
+        match self {
+            Self::AllyFleet(idx) => query.ally_fleet_force(idx),
+            Self::EnemyGuard => query.enemy_guard_force(),
+            Self::EnemyFleet(idx) => query.enemy_fleet_force(idx),
+        }
+    }
+
+    /// query における対象艦隊の兵力を設定する。
+    pub fn set_force(self, query: &mut Query, force: FleetForce) {
+        match self {
+            Self::AllyFleet(idx) => query.set_ally_fleet_force(idx, force),
+            Self::EnemyGuard => query.set_enemy_guard_force(force),
+            Self::EnemyFleet(idx) => query.set_enemy_fleet_force(idx, force),
+        }
+    }
+}
+
+/// query の target の兵力を `FleetForce::MIN` から `FleetForce::MAX` まで変化させ、
+/// それぞれの兵力での戦闘結果を求める。target 以外の入力は query のまま。
+pub fn sweep_fleet_force(query: &Query, rules: &RuleSet, target: SweepTarget) -> Sweep {
+    let points = (FleetForce::MIN.inner()..=FleetForce::MAX.inner())
+        .map(|inner| {
+            let force = FleetForce::new(inner).expect("swept fleet force should be valid");
+
+            let mut query = query.clone();
+            target.set_force(&mut query, force);
+
+            SweepPoint {
+                force,
+                outcome: Outcome::new(&query, rules),
+            }
+        })
+        .collect();
+
+    Sweep {
+        target,
+        current: target.force(query),
+        points,
+    }
+}
+
+/// `sweep_fleet_force()` の結果。
+#[derive(Debug)]
+pub struct Sweep {
+    target: SweepTarget,
+    current: FleetForce,
+    points: Vec<SweepPoint>,
+}
+
+impl Sweep {
+    /// 兵力を変化させた艦隊を返す。
+    pub fn target(&self) -> SweepTarget {
+        self.target
+    }
+
+    /// 元の query における対象艦隊の兵力を返す。
+    pub fn current(&self) -> FleetForce {
+        self.current
+    }
+
+    /// 各兵力での結果を兵力の昇順に返す。
+    pub fn points(&self) -> &[SweepPoint] {
+        &self.points
+    }
+
+    /// 兵力 force での結果を返す。
+    pub fn point(&self, force: FleetForce) -> &SweepPoint {
+        &self.points[(force.inner() - FleetForce::MIN.inner()) as usize]
+    }
+
+    /// 兵力を 1 減らした場合と比べて 1 艦隊あたりのダメージ (または戦闘できるかどうか) が
+    /// 変わる点を兵力の昇順に返す。
+    pub fn breakpoints(&self) -> impl Iterator<Item = &SweepPoint> + '_ {
+        self.points
+            .windows(2)
+            .filter(|w| w[0].damages_per_fleet() != w[1].damages_per_fleet())
+            .map(|w| &w[1])
+    }
+
+    /// 現在の兵力より大きい最初のブレークポイントを返す。兵力をここまで増やすと結果が変わる。
+    pub fn next_breakpoint(&self) -> Option<&SweepPoint> {
+        self.breakpoints().find(|point| point.force > self.current)
+    }
+
+    /// 現在の兵力以下の最後のブレークポイントを返す。兵力をこれより減らすと結果が変わる。
+    pub fn prev_breakpoint(&self) -> Option<&SweepPoint> {
+        self.breakpoints()
+            .take_while(|point| point.force <= self.current)
+            .last()
+    }
+}
+
+/// `Sweep` 内の 1 つの兵力での結果。
+#[derive(Debug)]
+pub struct SweepPoint {
+    force: FleetForce,
+    outcome: Option<Outcome>,
+}
+
+impl SweepPoint {
+    /// 対象艦隊の兵力を返す。
+    pub fn force(&self) -> FleetForce {
+        self.force
+    }
+
+    /// 戦闘結果を返す。戦闘できない入力 (味方の兵力が全て 0 など) なら `None` を返す。
+    pub fn outcome(&self) -> Option<&Outcome> {
+        self.outcome.as_ref()
+    }
+
+    /// (敵の 1 艦隊あたりのダメージ, 味方の 1 艦隊あたりのダメージ) を返す。
+    /// 戦闘できない入力なら `None` を返す。
+    pub fn damages_per_fleet(&self) -> Option<(u32, u32)> {
+        self.outcome.as_ref().map(|outcome| {
+            let report = outcome.report();
+            (
+                report.enemy_damage_per_fleet(),
+                report.ally_damage_per_fleet(),
+            )
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use crate::battle::*;
+use crate::util;
+
+/// 兵力を均等に配分した味方で、敵陣営を round_count ラウンド以内に全滅させられる最小の総兵力を求める。
+///
+/// query の敵陣営と味方のフォーメーションを使い、味方の兵力と疲労状態は無視する。
+/// 味方は全艦隊が攻撃に参加するものとする。
+///
+/// 総兵力を小さい方から順に試し、各総兵力について艦隊数を少ない方から順に試す。
+/// 兵力は各艦隊に均等に配分する (端数は若い番号の艦隊に 1 ずつ足す)。
+///
+/// 1 ラウンドの場合、全滅できるかどうかは配分の仕方に依存しない (艦隊数にのみ依存する) ので、
+/// 結果は真の最小値になる。2 ラウンド以上の場合は味方艦隊の全滅や艦隊数の減少が配分に依存する
+/// ため、均等配分に限った最小値 (真の最小値の上界) にすぎない。
+///
+/// 敵陣営が不正な場合、または味方の全兵力でも全滅させられない場合は `None` を返す。
+pub fn solve_min_ally_force(
+    query: &Query,
+    rules: &RuleSet,
+    round_count: usize,
+) -> Option<Solution> {
+    let force_max = util::u32_from_usize(ALLY_FLEET_COUNT) * FleetForce::MAX.inner();
+
+    (1..=force_max).find_map(|force_total| {
+        let fleet_count_min = force_total.div_ceil(FleetForce::MAX.inner());
+        let fleet_count_max = force_total.min(util::u32_from_usize(ALLY_FLEET_COUNT));
+
+        (fleet_count_min..=fleet_count_max).find_map(|fleet_count| {
+            let query = query_with_ally_split(query, force_total, fleet_count);
+            let rounds = battle_simulate_rounds(&query, rules, round_count, |_| false)?;
+
+            let report = rounds.reports().last()?;
+            report.enemy_is_annihilated().then(|| Solution {
+                query,
+                force_total,
+                round_count: rounds.reports().len(),
+            })
+        })
+    })
+}
+
+/// 味方の総兵力 force_total を fleet_count 個の艦隊に均等に配分した query を返す。
+fn query_with_ally_split(query: &Query, force_total: u32, fleet_count: u32) -> Query {
+    let mut query = query.clone();
+
+    let quot = force_total / fleet_count;
+    let rem = force_total % fleet_count;
+
+    for i in 0..ALLY_FLEET_COUNT {
+        let i_u32 = util::u32_from_usize(i);
+        let force = if i_u32 < rem {
+            quot + 1
+        } else if i_u32 < fleet_count {
+            quot
+        } else {
+            0
+        };
+        let force = FleetForce::new(force).expect("split fleet force should be valid");
+
+        query.set_ally_fleet_force(i, force);
+        query.set_ally_fleet_is_tired(i, false);
+    }
+
+    query
+}
+
+/// `solve_min_ally_force()` の解。
+#[derive(Debug)]
+pub struct Solution {
+    query: Query,
+    force_total: u32,
+    round_count: usize,
+}
+
+impl Solution {
+    /// 味方の兵力を解の配分にした query を返す。
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// 味方の総兵力を返す。
+    pub fn force_total(&self) -> u32 {
+        self.force_total
+    }
+
+    /// 敵陣営が全滅するまでにかかったラウンド数を返す。
+    pub fn round_count(&self) -> usize {
+        self.round_count
+    }
+}
@@ -1,3 +1,7 @@
 pub(crate) fn u32_from_usize(x: usize) -> u32 {
     u32::try_from(x).expect("u32_from_usize() failed")
 }
+
+pub(crate) fn u64_from_usize(x: usize) -> u64 {
+    u64::try_from(x).expect("u64_from_usize() failed")
+}
@@ -1,23 +1,169 @@
+use std::collections::HashMap;
+
 use seed::{prelude::*, *};
 
+use crate::allocate::*;
 use crate::battle::*;
+use crate::campaign::*;
+use crate::recommend::*;
+use crate::sensitivity::*;
+use crate::solve::*;
+
+mod history;
+mod permalink;
+mod storage;
 
 const CLASS_HEADER_ALLY: &str = "header-ally";
 const CLASS_HEADER_ENEMY: &str = "header-enemy";
 const CLASS_INPUT_FLEET_FORCE: &str = "input-fleet-force";
 const CLASS_INPUT_FLEET_FORCE_DEAD: &str = "input-fleet-force-dead";
 const CLASS_INPUT_FORMATION: &str = "input-formation";
+const CLASS_INPUT_INVALID: &str = "input-invalid";
+const CLASS_INPUT_ERROR: &str = "input-error";
 const CLASS_OUTPUT_FLEET_FORCE: &str = "output-fleet-force";
 const CLASS_OUTPUT_FLEET_FORCE_DEAD: &str = "output-fleet-force-dead";
+const CLASS_INPUT_PROB: &str = "input-prob";
+const CLASS_INPUT_NUMBER: &str = "input-number";
+const CLASS_INPUT_ABILITIES: &str = "input-abilities";
+const CLASS_OUTPUT_FATIGUE_TIRED: &str = "output-fatigue-tired";
+const CLASS_MATRIX_CURRENT: &str = "matrix-current";
+const CLASS_MATRIX_BEST: &str = "matrix-best";
+const CLASS_MATRIX_CLICKABLE: &str = "matrix-clickable";
+const CLASS_HISTORY_LIST: &str = "history-list";
+const CLASS_HISTORY_UNDONE: &str = "history-undone";
+const CLASS_SCENARIO_ACTIVE: &str = "scenario-active";
+const CLASS_COMPARE_BEST: &str = "compare-best";
+const CLASS_SWEEP_ALLY: &str = "sweep-ally";
+const CLASS_SWEEP_ENEMY: &str = "sweep-enemy";
+const CLASS_SWEEP_CURRENT: &str = "sweep-current";
+const CLASS_SWEEP_STEP_CURRENT: &str = "sweep-step-current";
+
+/// 連続戦闘の最大ラウンド数。
+const ROUND_MAX: usize = 20;
+
+/// 疲労度の推移を求める際の戦闘回数のデフォルト値。
+const CAMPAIGN_BATTLE_COUNT: usize = 3;
+
+/// 疲労度の推移を求める際の戦闘回数の上限。描画のたびに計算するので大きくしすぎない。
+const CAMPAIGN_BATTLE_COUNT_MAX: usize = 100;
+
+/// 必要兵力を求める際の最大ラウンド数。
+const SOLVE_ROUND_MAX: usize = 3;
 
 #[wasm_bindgen(start)]
 pub fn start() {
     App::start("app", init, update, view);
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct Model {
     query: Query,
+    rules: RuleSet,
+    objective: Objective,
+    enemy_formation_probs: [u32; Formation::COUNT],
+    ally_fleet_fatigues: AllyFatigues,
+    fatigue_rule: FatigueRule,
+    campaign_battle_count: usize,
+    /// 値として解釈できなかった入力欄。query には反映されず、前の値のまま。
+    invalid_inputs: HashMap<InputField, InvalidInput>,
+    history: history::History,
+    /// 比較用のシナリオ。アクティブなシナリオの query は常に `query` と同じ。
+    scenarios: Vec<Scenario>,
+    active_scenario: usize,
+    /// localStorage に保存したプリセット。
+    presets: Vec<storage::NamedQuery>,
+    /// プリセットを保存する際の名前の入力欄。
+    preset_name: String,
+    /// localStorage の読み書きに失敗した場合のエラーメッセージ。
+    storage_error: Option<String>,
+    /// 保存済みのプリセットを読み込めなかった場合のエラーメッセージ。保存済みのデータを上書き
+    /// しないよう、これが `Some` の間はプリセットを保存しない。
+    presets_load_error: Option<String>,
+    /// 感度分析で兵力を変化させる艦隊。
+    sweep_target: SweepTarget,
+    /// 必要兵力の計算結果。計算が重いので、ボタンを押したときだけ求める。
+    solve_results: Option<SolveResults>,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self {
+            query: Query::default(),
+            rules: RuleSet::default(),
+            objective: Objective::default(),
+            enemy_formation_probs: [1; Formation::COUNT],
+            ally_fleet_fatigues: [Fatigue::zero(); ALLY_FLEET_COUNT],
+            fatigue_rule: FatigueRule::default(),
+            campaign_battle_count: CAMPAIGN_BATTLE_COUNT,
+            invalid_inputs: HashMap::new(),
+            history: history::History::default(),
+            scenarios: vec![Scenario::new(scenario_name(0), Query::default())],
+            active_scenario: 0,
+            presets: Vec::new(),
+            preset_name: String::new(),
+            storage_error: None,
+            presets_load_error: None,
+            sweep_target: SweepTarget::AllyFleet(0),
+            solve_results: None,
+        }
+    }
+}
+
+/// 必要兵力の計算結果。
+#[derive(Debug)]
+struct SolveResults {
+    /// 計算に使った入力。現在の入力と異なれば結果は古い。
+    query: Query,
+    /// ラウンド数 1 から `SOLVE_ROUND_MAX` までの解。
+    solutions: Vec<Option<Solution>>,
+}
+
+/// 名前付きの入力。
+#[derive(Debug)]
+struct Scenario {
+    name: String,
+    query: Query,
+    /// 非アクティブな間の編集履歴。アクティブな間は `Model::history` が持つ。
+    history: history::History,
+}
+
+impl Scenario {
+    fn new(name: String, query: Query) -> Self {
+        Self {
+            name,
+            query,
+            history: history::History::default(),
+        }
+    }
+}
+
+fn scenario_name(idx: usize) -> String {
+    format!("シナリオ{}", idx + 1)
+}
+
+/// 不正な値を保持しうる入力欄。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum InputField {
+    AllyFleetForce(usize),
+    AllyFormation,
+    AllyAbilities,
+    EnemyFleetForce(usize),
+    EnemyGuardForce,
+    EnemyFormation,
+    EnemyAbilities,
+    EnemyFormationProb(Formation),
+    AllyFleetFatigue(usize),
+    FatigueGainPerRound,
+    FatigueRecoveryPerTurn,
+    FatigueRestTurns,
+    CampaignBattleCount,
+}
+
+/// 入力欄に入力された不正な文字列とエラーメッセージ。
+#[derive(Debug)]
+struct InvalidInput {
+    text: String,
+    error: String,
 }
 
 #[derive(Debug)]
@@ -25,17 +171,117 @@ enum Msg {
     SetAllyFleetForce(usize, FleetForce),
     ToggleAllyFleetIsTired(usize),
     SetAllyFormation(Formation),
+    SetAllyAbilities(Vec<Ability>),
     SetEnemyFleetForce(usize, FleetForce),
+    ToggleEnemyFleetIsTired(usize),
     SetEnemyGuardForce(FleetForce),
+    ToggleEnemyGuardIsTired,
     SetEnemyFormation(Formation),
     ToggleEnemyHasYang,
+    SetEnemyAbilities(Vec<Ability>),
+    SetObjective(Objective),
+    SetEnemyFormationProb(Formation, u32),
+    SetFormations(Formation, Formation),
+    SetInvalidInput(InputField, InvalidInput),
+    SetAllyFleetFatigue(usize, Fatigue),
+    SetFatigueGainPerRound(u32),
+    SetFatigueRecoveryPerTurn(u32),
+    SetFatigueRestTurns(u32),
+    SetCampaignBattleCount(usize),
+    Undo,
+    Redo,
+    AddScenario,
+    RemoveScenario(usize),
+    SelectScenario(usize),
+    RenameScenario(usize, String),
+    SetPresetName(String),
+    SavePreset,
+    LoadPreset(usize),
+    RenamePreset(usize, String),
+    DeletePreset(usize),
+    DiscardStoredPresets,
+    SetSweepTarget(SweepTarget),
+    RestAllyFleets(Vec<usize>),
+    SolveMinAllyForce,
 }
 
-fn init(_url: Url, _orders: &mut impl Orders<Msg>) -> Model {
-    Model::default()
+fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
+    orders.stream(streams::window_event(Ev::KeyDown, |event| {
+        shortcut_msg(event.unchecked_ref())
+    }));
+
+    let mut model = Model::default();
+
+    match storage::load_presets() {
+        Ok(presets) => model.presets = presets,
+        Err(e) => model.presets_load_error = Some(format!("{e:#}")),
+    }
+
+    // 前回のセッションを復元する。
+    match storage::load_session() {
+        Ok(Some(session)) => {
+            model.scenarios = session
+                .scenarios
+                .into_iter()
+                .map(|named| Scenario::new(named.name, named.query))
+                .collect();
+            model.active_scenario = session.active;
+            model.query = model.scenarios[session.active].query.clone();
+        }
+        Ok(None) => {}
+        Err(e) => model.storage_error = Some(format!("{e:#}")),
+    }
+
+    if url.search().iter().next().is_some() {
+        // URL に入力があれば、アクティブなシナリオをそれで置き換える。
+        // URL が不正な場合はデフォルト値で始める。
+        model.query = permalink::query_from_search(url.search()).unwrap_or_default();
+        model.scenarios[model.active_scenario].query = model.query.clone();
+    } else {
+        Url::current()
+            .set_search(permalink::query_to_search(&model.query))
+            .go_and_replace();
+    }
+
+    // URL やセッションには疲労フラグしかないので、疲労度はフラグから決める。
+    model.ally_fleet_fatigues =
+        std::array::from_fn(|i| fatigue_from_is_tired(model.query.ally_fleet_is_tired(i)));
+
+    model
 }
 
 fn update(msg: Msg, model: &mut Model, _orders: &mut impl Orders<Msg>) {
+    let query_old = model.query.clone();
+    // 履歴の移動やシナリオの切り替えは編集として記録しない。
+    let is_edit = !matches!(
+        msg,
+        Msg::Undo | Msg::Redo | Msg::RemoveScenario(_) | Msg::SelectScenario(_)
+    );
+
+    update_model(msg, model);
+
+    if is_edit {
+        model.history.record(&query_old, &model.query);
+    }
+    model.scenarios[model.active_scenario].query = model.query.clone();
+
+    let result = storage::save_session(&session_from_model(model));
+    record_storage_result(model, result);
+
+    // 入力が変わったら URL に反映する (履歴は増やさない)。
+    if model.query != query_old {
+        Url::current()
+            .set_search(permalink::query_to_search(&model.query))
+            .go_and_replace();
+    }
+}
+
+fn update_model(msg: Msg, model: &mut Model) {
+    // 正しい値が入力されたら、その欄のエラーを消す。
+    for field in msg.input_fields() {
+        model.invalid_inputs.remove(&field);
+    }
+
     match msg {
         Msg::SetAllyFleetForce(idx, fleet_force) => {
             model.query.set_ally_fleet_force(idx, fleet_force)
@@ -44,27 +290,361 @@ fn update(msg: Msg, model: &mut Model, _orders: &mut impl Orders<Msg>) {
         Msg::ToggleAllyFleetIsTired(idx) => {
             let value = !model.query.ally_fleet_is_tired(idx);
             model.query.set_ally_fleet_is_tired(idx, value);
+            model.ally_fleet_fatigues[idx] = fatigue_from_is_tired(value);
         }
 
         Msg::SetAllyFormation(formation) => model.query.set_ally_formation(formation),
 
+        Msg::SetAllyAbilities(abilities) => model.query.set_ally_abilities(abilities),
+
         Msg::SetEnemyFleetForce(idx, fleet_force) => {
             model.query.set_enemy_fleet_force(idx, fleet_force)
         }
 
+        Msg::ToggleEnemyFleetIsTired(idx) => {
+            let value = !model.query.enemy_fleet_is_tired(idx);
+            model.query.set_enemy_fleet_is_tired(idx, value);
+        }
+
         Msg::SetEnemyGuardForce(fleet_force) => model.query.set_enemy_guard_force(fleet_force),
 
+        Msg::ToggleEnemyGuardIsTired => {
+            let value = !model.query.enemy_guard_is_tired();
+            model.query.set_enemy_guard_is_tired(value);
+        }
+
         Msg::SetEnemyFormation(formation) => model.query.set_enemy_formation(formation),
 
         Msg::ToggleEnemyHasYang => {
             let value = !model.query.enemy_has_yang();
             model.query.set_enemy_has_yang(value);
         }
+
+        Msg::SetEnemyAbilities(abilities) => model.query.set_enemy_abilities(abilities),
+
+        Msg::SetObjective(objective) => model.objective = objective,
+
+        Msg::SetEnemyFormationProb(formation, prob) => {
+            model.enemy_formation_probs[formation.index()] = prob
+        }
+
+        Msg::SetFormations(ally_formation, enemy_formation) => {
+            model.query.set_ally_formation(ally_formation);
+            model.query.set_enemy_formation(enemy_formation);
+        }
+
+        Msg::SetInvalidInput(field, invalid) => {
+            model.invalid_inputs.insert(field, invalid);
+        }
+
+        Msg::SetAllyFleetFatigue(idx, fatigue) => {
+            model.ally_fleet_fatigues[idx] = fatigue;
+            model.query.set_ally_fleet_is_tired(idx, fatigue.is_tired());
+        }
+
+        Msg::SetFatigueGainPerRound(gain_per_round) => {
+            let rule = model.fatigue_rule;
+            model.fatigue_rule =
+                FatigueRule::new(gain_per_round, rule.recovery_per_turn(), rule.rest_turns());
+        }
+
+        Msg::SetFatigueRecoveryPerTurn(recovery_per_turn) => {
+            let rule = model.fatigue_rule;
+            model.fatigue_rule =
+                FatigueRule::new(rule.gain_per_round(), recovery_per_turn, rule.rest_turns());
+        }
+
+        Msg::SetFatigueRestTurns(rest_turns) => {
+            let rule = model.fatigue_rule;
+            model.fatigue_rule =
+                FatigueRule::new(rule.gain_per_round(), rule.recovery_per_turn(), rest_turns);
+        }
+
+        Msg::SetCampaignBattleCount(count) => {
+            model.campaign_battle_count = count.min(CAMPAIGN_BATTLE_COUNT_MAX)
+        }
+
+        Msg::Undo => {
+            if let Some(query) = model.history.undo() {
+                restore_query(model, query);
+            }
+        }
+
+        Msg::Redo => {
+            if let Some(query) = model.history.redo() {
+                restore_query(model, query);
+            }
+        }
+
+        Msg::AddScenario => {
+            // アクティブなシナリオを複製して切り替える。
+            let idx = model.scenarios.len();
+            model
+                .scenarios
+                .push(Scenario::new(scenario_name(idx), model.query.clone()));
+            select_scenario(model, idx);
+        }
+
+        Msg::RemoveScenario(idx) => {
+            let len = model.scenarios.len();
+            if len <= 1 || idx >= len {
+                return;
+            }
+
+            if idx == model.active_scenario {
+                select_scenario(model, if idx + 1 < len { idx + 1 } else { idx - 1 });
+            }
+            model.scenarios.remove(idx);
+            if idx < model.active_scenario {
+                model.active_scenario -= 1;
+            }
+        }
+
+        Msg::SelectScenario(idx) => select_scenario(model, idx),
+
+        Msg::RenameScenario(idx, name) => {
+            if let Some(scenario) = model.scenarios.get_mut(idx) {
+                scenario.name = name;
+            }
+        }
+
+        Msg::SetPresetName(name) => model.preset_name = name,
+
+        Msg::SavePreset if model.presets_load_error.is_some() => {}
+
+        Msg::SavePreset => {
+            // 名前が空ならシナリオ名を使う。同じ名前のプリセットがあれば上書きする。
+            let name = match model.preset_name.trim() {
+                "" => model.scenarios[model.active_scenario].name.clone(),
+                name => name.to_owned(),
+            };
+            let preset = storage::NamedQuery {
+                name,
+                query: model.query.clone(),
+            };
+            match model.presets.iter_mut().find(|p| p.name == preset.name) {
+                Some(p) => *p = preset,
+                None => model.presets.push(preset),
+            }
+            model.preset_name.clear();
+            save_presets(model);
+        }
+
+        Msg::LoadPreset(idx) => {
+            if let Some(preset) = model.presets.get(idx) {
+                let query = preset.query.clone();
+                restore_query(model, query);
+            }
+        }
+
+        Msg::RenamePreset(idx, name) => {
+            if let Some(preset) = model.presets.get_mut(idx) {
+                preset.name = name;
+                save_presets(model);
+            }
+        }
+
+        Msg::DeletePreset(idx) => {
+            if idx < model.presets.len() {
+                model.presets.remove(idx);
+                save_presets(model);
+            }
+        }
+
+        Msg::DiscardStoredPresets => {
+            model.presets_load_error = None;
+            save_presets(model);
+        }
+
+        Msg::SetSweepTarget(target) => model.sweep_target = target,
+
+        Msg::RestAllyFleets(idxs) => {
+            for idx in idxs {
+                model.query.set_ally_fleet_is_tired(idx, true);
+                model.ally_fleet_fatigues[idx] = fatigue_from_is_tired(true);
+            }
+        }
+
+        Msg::SolveMinAllyForce => {
+            let solutions = (1..=SOLVE_ROUND_MAX)
+                .map(|round_count| solve_min_ally_force(&model.query, &model.rules, round_count))
+                .collect();
+            model.solve_results = Some(SolveResults {
+                query: model.query.clone(),
+                solutions,
+            });
+        }
+    }
+}
+
+fn session_from_model(model: &Model) -> storage::Session {
+    storage::Session {
+        scenarios: model
+            .scenarios
+            .iter()
+            .map(|scenario| storage::NamedQuery {
+                name: scenario.name.clone(),
+                query: scenario.query.clone(),
+            })
+            .collect(),
+        active: model.active_scenario,
+    }
+}
+
+fn save_presets(model: &mut Model) {
+    let result = storage::save_presets(&model.presets);
+    record_storage_result(model, result);
+}
+
+fn record_storage_result(model: &mut Model, result: anyhow::Result<()>) {
+    model.storage_error = result.err().map(|e| format!("{e:#}"));
+}
+
+/// シナリオ idx をアクティブにし、その query と編集履歴を読み込む。
+fn select_scenario(model: &mut Model, idx: usize) {
+    if idx == model.active_scenario || idx >= model.scenarios.len() {
+        return;
+    }
+
+    let active = &mut model.scenarios[model.active_scenario];
+    active.query = model.query.clone();
+    active.history = std::mem::take(&mut model.history);
+
+    model.active_scenario = idx;
+    let scenario = &mut model.scenarios[idx];
+    model.history = std::mem::take(&mut scenario.history);
+    let query = scenario.query.clone();
+    restore_query(model, query);
+}
+
+/// 履歴から取り出した query に戻す。
+fn restore_query(model: &mut Model, query: Query) {
+    // 疲労度は疲労フラグが変わった艦隊だけフラグから決め直す。
+    for (i, fatigue) in model.ally_fleet_fatigues.iter_mut().enumerate() {
+        let is_tired = query.ally_fleet_is_tired(i);
+        if fatigue.is_tired() != is_tired {
+            *fatigue = fatigue_from_is_tired(is_tired);
+        }
+    }
+
+    model.query = query;
+    model.invalid_inputs.clear();
+}
+
+/// キーボードショートカットに対応するメッセージを返す。
+///
+/// Ctrl+Z (Mac では Cmd+Z) で元に戻し、Ctrl+Y または Ctrl+Shift+Z でやり直す。
+/// 文字入力欄の編集中はブラウザ標準の動作に任せる。
+fn shortcut_msg(event: &web_sys::KeyboardEvent) -> Option<Msg> {
+    if !(event.ctrl_key() || event.meta_key()) || event.alt_key() {
+        return None;
+    }
+
+    if let Some(target) = event.target() {
+        if let Some(input) = target.dyn_ref::<web_sys::HtmlInputElement>() {
+            if !matches!(input.type_().as_str(), "checkbox" | "radio" | "button") {
+                return None;
+            }
+        }
+    }
+
+    let msg = match (event.key().to_lowercase().as_str(), event.shift_key()) {
+        ("z", false) => Msg::Undo,
+        ("z", true) | ("y", false) => Msg::Redo,
+        _ => return None,
+    };
+    event.prevent_default();
+
+    Some(msg)
+}
+
+impl Msg {
+    /// 正しい値を設定するメッセージなら、対応する入力欄を返す。
+    fn input_fields(&self) -> Vec<InputField> {
+        match *self {
+            Self::SetAllyFleetForce(idx, _) => vec![InputField::AllyFleetForce(idx)],
+            Self::SetAllyFormation(_) => vec![InputField::AllyFormation],
+            Self::SetAllyAbilities(_) => vec![InputField::AllyAbilities],
+            Self::SetEnemyFleetForce(idx, _) => vec![InputField::EnemyFleetForce(idx)],
+            Self::SetEnemyGuardForce(_) => vec![InputField::EnemyGuardForce],
+            Self::SetEnemyFormation(_) => vec![InputField::EnemyFormation],
+            // ヤンの有無は敵の能力の値を変えるので、能力欄の不正な入力は破棄する。
+            Self::SetEnemyAbilities(_) | Self::ToggleEnemyHasYang => {
+                vec![InputField::EnemyAbilities]
+            }
+            Self::SetFormations(..) => vec![InputField::AllyFormation, InputField::EnemyFormation],
+            Self::SetEnemyFormationProb(formation, _) => {
+                vec![InputField::EnemyFormationProb(formation)]
+            }
+            Self::SetAllyFleetFatigue(idx, _) => vec![InputField::AllyFleetFatigue(idx)],
+            Self::SetFatigueGainPerRound(_) => vec![InputField::FatigueGainPerRound],
+            Self::SetFatigueRecoveryPerTurn(_) => vec![InputField::FatigueRecoveryPerTurn],
+            Self::SetFatigueRestTurns(_) => vec![InputField::FatigueRestTurns],
+            Self::SetCampaignBattleCount(_) => vec![InputField::CampaignBattleCount],
+            _ => vec![],
+        }
     }
 }
 
 fn view(model: &Model) -> Node<Msg> {
-    div![view_query(model), view_report(model)]
+    div![
+        view_scenarios(model),
+        view_query(model),
+        view_history(model),
+        view_presets(model),
+        view_report(model),
+        view_compare(model),
+        view_trace(model),
+        view_rounds(model),
+        view_campaign(model),
+        view_coef(model),
+        view_matrix(model),
+        view_solve(model),
+        view_sensitivity(model),
+        view_allocate(model),
+    ]
+}
+
+fn view_scenarios(model: &Model) -> Node<Msg> {
+    const ID_INPUT: &str = "input-scenario-name";
+
+    let tabs = model.scenarios.iter().enumerate().map(|(i, scenario)| {
+        button![
+            C!(IF!(i == model.active_scenario => CLASS_SCENARIO_ACTIVE)),
+            &scenario.name,
+            ev(Ev::Click, move |_| Msg::SelectScenario(i)),
+        ]
+    });
+
+    let active = model.active_scenario;
+
+    div![
+        p![tabs, button!["+ 複製", ev(Ev::Click, |_| Msg::AddScenario)],],
+        p![
+            label![
+                attrs! {
+                    At::For => ID_INPUT,
+                },
+                "シナリオ名: ",
+            ],
+            input![
+                id!(ID_INPUT),
+                attrs! {
+                    At::Type => "text",
+                    At::Value => model.scenarios[active].name,
+                },
+                input_ev(Ev::Change, move |s| Msg::RenameScenario(active, s)),
+            ],
+            " ",
+            button![
+                "削除",
+                attrs! {
+                    At::Disabled => (model.scenarios.len() <= 1).as_at_value(),
+                },
+                ev(Ev::Click, move |_| Msg::RemoveScenario(active)),
+            ],
+        ],
+    ]
 }
 
 fn view_query(model: &Model) -> Node<Msg> {
@@ -79,13 +659,110 @@ fn view_query_ally(model: &Model) -> Node<Msg> {
     div![
         h3![C!(CLASS_HEADER_ALLY), "味方"],
         view_query_ally_formation(model),
-        view_query_ally_fleets(model)
+        view_query_ally_recommend(model),
+        view_query_ally_fleets(model),
+        view_input_abilities(
+            model,
+            "input-ally-abilities",
+            InputField::AllyAbilities,
+            model.query.ally_abilities(),
+            Msg::SetAllyAbilities,
+        ),
     ]
 }
 
+fn view_query_ally_recommend(model: &Model) -> Node<Msg> {
+    const ID_SELECT: &str = "select-objective";
+
+    let options = Objective::ALL
+        .into_iter()
+        .enumerate()
+        .map(|(i, objective)| {
+            option![
+                attrs! {
+                    At::Value => i,
+                    At::Selected => (objective == model.objective).as_at_value(),
+                },
+                objective_name(objective),
+            ]
+        });
+
+    let select_objective = p![
+        label![
+            attrs! {
+                At::For => ID_SELECT,
+            },
+            "フォーメーション評価基準: ",
+        ],
+        select![
+            id!(ID_SELECT),
+            options,
+            input_ev(Ev::Change, |s| {
+                let i: usize = s.parse().ok()?;
+                Objective::ALL.get(i).copied().map(Msg::SetObjective)
+            }),
+        ],
+    ];
+
+    let rows = recommend_formations(&model.query, &model.rules, model.objective)
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, outcome)| {
+            let formation = outcome.ally_formation();
+            let report = outcome.report();
+            tr![
+                td![i + 1],
+                td![button![
+                    formation.to_string(),
+                    ev(Ev::Click, move |_| Msg::SetAllyFormation(formation)),
+                ]],
+                td![report.ally_formation().to_string()],
+                td![if outcome.ability_is_effective() {
+                    "あり"
+                } else {
+                    "なし"
+                }],
+                td![outcome.enemy_damage()],
+                td![outcome.ally_loss()],
+                td![outcome.net_damage()],
+                td![outcome.fleets_destroyed()],
+            ]
+        });
+
+    details![
+        summary!["フォーメーション比較"],
+        select_objective,
+        table![
+            thead![tr![
+                th!["順位"],
+                th!["フォーメーション"],
+                th!["修正後"],
+                th!["能力補正"],
+                th!["敵の損害"],
+                th!["味方の損害"],
+                th!["差引"],
+                th!["撃破艦隊数"],
+            ]],
+            tbody![rows],
+        ],
+    ]
+}
+
+fn objective_name(objective: Objective) -> &'static str {
+    match objective {
+        Objective::EnemyDamage => "敵の損害",
+        Objective::AllyLoss => "味方の損害",
+        Objective::NetDamage => "差引 (敵の損害 - 味方の損害)",
+        Objective::FleetsDestroyed => "撃破艦隊数",
+    }
+}
+
 fn view_query_ally_formation(model: &Model) -> Node<Msg> {
     view_input_formation(
+        model,
         "input-ally-formation",
+        InputField::AllyFormation,
         model.query.ally_formation(),
         Msg::SetAllyFormation,
     )
@@ -97,7 +774,12 @@ fn view_query_ally_fleets(model: &Model) -> Node<Msg> {
     let cols_force = (0..ALLY_FLEET_COUNT).map(|i| {
         let value = model.query.ally_fleet_force(i);
         let on_change = move |fleet_force| Msg::SetAllyFleetForce(i, fleet_force);
-        td![view_input_fleet_force(value, on_change)]
+        td![view_input_fleet_force(
+            model,
+            InputField::AllyFleetForce(i),
+            value,
+            on_change
+        )]
     });
 
     let cols_tired = (0..ALLY_FLEET_COUNT).map(|i| {
@@ -125,12 +807,21 @@ fn view_query_enemy(model: &Model) -> Node<Msg> {
         view_query_enemy_formation(model),
         view_query_enemy_fleets(model),
         view_query_enemy_yang(model),
+        view_input_abilities(
+            model,
+            "input-enemy-abilities",
+            InputField::EnemyAbilities,
+            model.query.enemy_abilities(),
+            Msg::SetEnemyAbilities,
+        ),
     ]
 }
 
 fn view_query_enemy_formation(model: &Model) -> Node<Msg> {
     view_input_formation(
+        model,
         "input-enemy-formation",
+        InputField::EnemyFormation,
         model.query.enemy_formation(),
         Msg::SetEnemyFormation,
     )
@@ -141,20 +832,55 @@ fn view_query_enemy_fleets(model: &Model) -> Node<Msg> {
 
     let col_guard_force = {
         let value = model.query.enemy_guard_force();
-        td![view_input_fleet_force(value, Msg::SetEnemyGuardForce)]
+        td![view_input_fleet_force(
+            model,
+            InputField::EnemyGuardForce,
+            value,
+            Msg::SetEnemyGuardForce
+        )]
     };
 
+    let col_guard_tired = td![input![
+        attrs! {
+            At::Type => "checkbox",
+            At::Checked => model.query.enemy_guard_is_tired().as_at_value(),
+        },
+        ev(Ev::Change, |_| Msg::ToggleEnemyGuardIsTired),
+    ]];
+
     let cols_fleet_header = (0..ENEMY_FLEET_COUNT).map(|i| th![i + 1]);
 
     let cols_fleet_force = (0..ENEMY_FLEET_COUNT).map(|i| {
         let value = model.query.enemy_fleet_force(i);
         let on_change = move |fleet_force| Msg::SetEnemyFleetForce(i, fleet_force);
-        td![view_input_fleet_force(value, on_change)]
+        td![view_input_fleet_force(
+            model,
+            InputField::EnemyFleetForce(i),
+            value,
+            on_change
+        )]
+    });
+
+    let cols_fleet_tired = (0..ENEMY_FLEET_COUNT).map(|i| {
+        td![input![
+            attrs! {
+                At::Type => "checkbox",
+                At::Checked => model.query.enemy_fleet_is_tired(i).as_at_value(),
+            },
+            ev(Ev::Change, move |_| Msg::ToggleEnemyFleetIsTired(i)),
+        ]]
     });
 
     table![
         thead![tr![th![], col_guard_header, cols_fleet_header]],
-        tbody![tr![th![label!["兵力"]], col_guard_force, cols_fleet_force]],
+        tbody![
+            tr![th![label!["兵力"]], col_guard_force, cols_fleet_force],
+            tr![
+                th![label!["疲労度80以上"]],
+                col_guard_tired,
+                cols_fleet_tired
+            ],
+        ],
     ]
 }
 
@@ -179,13 +905,122 @@ fn view_query_enemy_yang(model: &Model) -> Node<Msg> {
     ]
 }
 
+fn view_history(model: &Model) -> Node<Msg> {
+    let items_done = model
+        .history
+        .done()
+        .iter()
+        .map(|edit| li![edit.description()]);
+    let items_undone = model
+        .history
+        .undone()
+        .map(|edit| li![C!(CLASS_HISTORY_UNDONE), edit.description()]);
+
+    details![
+        summary!["編集履歴"],
+        p![
+            button![
+                "元に戻す (Ctrl+Z)",
+                attrs! {
+                    At::Disabled => (!model.history.can_undo()).as_at_value(),
+                },
+                ev(Ev::Click, |_| Msg::Undo),
+            ],
+            " ",
+            button![
+                "やり直す (Ctrl+Y)",
+                attrs! {
+                    At::Disabled => (!model.history.can_redo()).as_at_value(),
+                },
+                ev(Ev::Click, |_| Msg::Redo),
+            ],
+        ],
+        ol![C!(CLASS_HISTORY_LIST), items_done, items_undone],
+    ]
+}
+
+fn view_presets(model: &Model) -> Node<Msg> {
+    const ID_INPUT: &str = "input-preset-name";
+
+    let rows = model.presets.iter().enumerate().map(|(i, preset)| {
+        tr![
+            td![input![
+                attrs! {
+                    At::Type => "text",
+                    At::Value => preset.name,
+                },
+                input_ev(Ev::Change, move |s| Msg::RenamePreset(i, s)),
+            ]],
+            td![button![
+                "読み込む",
+                ev(Ev::Click, move |_| Msg::LoadPreset(i))
+            ]],
+            td![button![
+                "削除",
+                ev(Ev::Click, move |_| Msg::DeletePreset(i))
+            ]],
+        ]
+    });
+
+    details![
+        summary!["プリセット"],
+        p![
+            label![
+                attrs! {
+                    At::For => ID_INPUT,
+                },
+                "名前: ",
+            ],
+            input![
+                id!(ID_INPUT),
+                attrs! {
+                    At::Type => "text",
+                    At::Value => model.preset_name,
+                    At::Placeholder => model.scenarios[model.active_scenario].name,
+                },
+                input_ev(Ev::Input, Msg::SetPresetName),
+            ],
+            " ",
+            button![
+                "現在の入力を保存",
+                attrs! {
+                    At::Disabled => model.presets_load_error.is_some().as_at_value(),
+                },
+                ev(Ev::Click, |_| Msg::SavePreset),
+            ],
+        ],
+        model.presets_load_error.as_ref().map(|e| {
+            p![
+                C!(CLASS_INPUT_ERROR),
+                format!(
+                    "保存済みのプリセットを読み込めません: {e}。上書きしないよう、破棄するまで保存できません。 "
+                ),
+                button![
+                    "保存済みのプリセットを破棄",
+                    ev(Ev::Click, |_| Msg::DiscardStoredPresets),
+                ],
+            ]
+        }),
+        model
+            .storage_error
+            .as_ref()
+            .map(|e| p![C!(CLASS_INPUT_ERROR), format!("保存できません: {e}")]),
+        table![tbody![rows]],
+    ]
+}
+
 fn view_report(model: &Model) -> Node<Msg> {
     div![h2!["戦闘結果"], view_report_body(model)]
 }
 
-fn view_report_body(model: &Model) -> Option<Node<Msg>> {
-    battle_simulate(&model.query)
-        .map(|report| div![view_report_ally(&report), view_report_enemy(&report)])
+fn view_report_body(model: &Model) -> Node<Msg> {
+    if let Err(e) = model.query.validate() {
+        return p![C!(CLASS_INPUT_ERROR), format!("戦闘できません: {e}")];
+    }
+
+    let report = battle_simulate(&model.query, &model.rules).expect("query should be valid");
+
+    div![view_report_ally(&report), view_report_enemy(&report)]
 }
 
 fn view_report_ally(report: &Report) -> Node<Msg> {
@@ -241,10 +1076,1044 @@ fn view_report_enemy_fleets(report: &Report) -> Node<Msg> {
     ]
 }
 
-fn view_input_formation<F>(id: &str, value: Formation, on_change: F) -> Node<Msg>
+fn view_compare(model: &Model) -> Node<Msg> {
+    let reports: Vec<_> = model
+        .scenarios
+        .iter()
+        .map(|scenario| battle_simulate(&scenario.query, &model.rules))
+        .collect();
+
+    let enemy_damages: Vec<_> = model
+        .scenarios
+        .iter()
+        .zip(&reports)
+        .map(|(scenario, report)| {
+            report
+                .as_ref()
+                .map(|report| scenario.query.enemy_force_total() - report.enemy_force_total())
+        })
+        .collect();
+    let ally_losses: Vec<_> = model
+        .scenarios
+        .iter()
+        .zip(&reports)
+        .map(|(scenario, report)| {
+            report
+                .as_ref()
+                .map(|report| scenario.query.ally_force_total() - report.ally_force_total())
+        })
+        .collect();
+
+    let enemy_damage_best = enemy_damages.iter().flatten().max().copied();
+    let ally_loss_best = ally_losses.iter().flatten().min().copied();
+
+    let cols_header_ally = (0..ALLY_FLEET_COUNT).map(|i| th![C!(CLASS_HEADER_ALLY), i + 1]);
+    let cols_header_enemy = (0..ENEMY_FLEET_COUNT).map(|i| th![C!(CLASS_HEADER_ENEMY), i + 1]);
+
+    let rows = model.scenarios.iter().enumerate().map(|(i, scenario)| {
+        let query = &scenario.query;
+        let col_name = td![button![
+            C!(IF!(i == model.active_scenario => CLASS_SCENARIO_ACTIVE)),
+            &scenario.name,
+            ev(Ev::Click, move |_| Msg::SelectScenario(i)),
+        ]];
+
+        let Some(report) = &reports[i] else {
+            return tr![
+                col_name,
+                td![
+                    attrs! {
+                        At::ColSpan => 5 + ALLY_FLEET_COUNT + ENEMY_FLEET_COUNT,
+                    },
+                    "戦闘できません",
+                ]
+            ];
+        };
+
+        let (enemy_damage, ally_loss) = (enemy_damages[i], ally_losses[i]);
+
+        let cols_ally = (0..ALLY_FLEET_COUNT).map(|j| {
+            td![view_compare_delta(
+                query.ally_fleet_force(j),
+                report.ally_fleet_force(j)
+            )]
+        });
+        let cols_enemy = (0..ENEMY_FLEET_COUNT).map(|j| {
+            td![view_compare_delta(
+                query.enemy_fleet_force(j),
+                report.enemy_fleet_force(j)
+            )]
+        });
+
+        tr![
+            col_name,
+            td![report.ally_formation().to_string()],
+            td![report.enemy_formation().to_string()],
+            td![
+                C!(IF!(enemy_damage == enemy_damage_best => CLASS_COMPARE_BEST)),
+                enemy_damage,
+            ],
+            td![
+                C!(IF!(ally_loss == ally_loss_best => CLASS_COMPARE_BEST)),
+                ally_loss,
+            ],
+            cols_ally,
+            td![view_compare_delta(
+                query.enemy_guard_force(),
+                report.enemy_guard_force()
+            )],
+            cols_enemy,
+        ]
+    });
+
+    div![
+        h2!["シナリオ比較"],
+        p!["各艦隊の欄は兵力の増減 (× は全滅)。太字は敵の損害が最大、味方の損害が最小のシナリオ。"],
+        table![
+            thead![
+                tr![
+                    th![attrs! { At::RowSpan => 2 }, "シナリオ"],
+                    th![attrs! { At::ColSpan => 2 }, "修正後フォーメーション"],
+                    th![attrs! { At::RowSpan => 2 }, "敵の損害"],
+                    th![attrs! { At::RowSpan => 2 }, "味方の損害"],
+                    th![
+                        C!(CLASS_HEADER_ALLY),
+                        attrs! { At::ColSpan => ALLY_FLEET_COUNT },
+                        "味方",
+                    ],
+                    th![
+                        C!(CLASS_HEADER_ENEMY),
+                        attrs! { At::ColSpan => 1 + ENEMY_FLEET_COUNT },
+                        "敵",
+                    ],
+                ],
+                tr![
+                    th![C!(CLASS_HEADER_ALLY), "味方"],
+                    th![C!(CLASS_HEADER_ENEMY), "敵"],
+                    cols_header_ally,
+                    th![C!(CLASS_HEADER_ENEMY), "駐留"],
+                    cols_header_enemy,
+                ],
+            ],
+            tbody![rows],
+        ],
+    ]
+}
+
+/// 戦闘前後の兵力の増減を表示する。戦闘前から兵力 0 の艦隊は空欄。
+fn view_compare_delta(before: FleetForce, after: FleetForce) -> Node<Msg> {
+    if before.is_zero() {
+        return empty![];
+    }
+
+    let delta = before.inner() - after.inner();
+    let text = if after.is_zero() {
+        format!("-{delta} ×")
+    } else if delta == 0 {
+        "±0".to_owned()
+    } else {
+        format!("-{delta}")
+    };
+
+    output![
+        C!(
+            CLASS_OUTPUT_FLEET_FORCE,
+            IF!(after.is_zero() => CLASS_OUTPUT_FLEET_FORCE_DEAD),
+        ),
+        text,
+    ]
+}
+
+fn view_trace(model: &Model) -> Node<Msg> {
+    details![summary!["計算過程"], view_trace_body(model)]
+}
+
+fn view_trace_body(model: &Model) -> Option<Node<Msg>> {
+    let (_, trace) = battle_simulate_traced(&model.query, &model.rules)?;
+    let (ally, enemy) = (&trace.ally, &trace.enemy);
+
+    let row = |header: &str, ally_value: String, enemy_value: String| {
+        tr![th![header], td![ally_value], td![enemy_value]]
+    };
+
+    Some(div![
+        table![
+            thead![tr![
+                th![],
+                th![C!(CLASS_HEADER_ALLY), "味方"],
+                th![C!(CLASS_HEADER_ENEMY), "敵"],
+            ]],
+            tbody![
+                row(
+                    "健在な艦隊数",
+                    ally.fleet_count.to_string(),
+                    enemy.fleet_count.to_string(),
+                ),
+                row(
+                    "攻撃可能な総兵力",
+                    ally.attack_force.to_string(),
+                    enemy.attack_force.to_string(),
+                ),
+                row(
+                    "攻撃可能な総兵力 (clamp 済み)",
+                    ally.attack_force_clamped.to_string(),
+                    enemy.attack_force_clamped.to_string(),
+                ),
+                row(
+                    "フォーメーション",
+                    ally.formation.to_string(),
+                    enemy.formation.to_string(),
+                ),
+                row(
+                    "フォーメーション修正判定値",
+                    trace_formation_ratio(ally, &model.rules),
+                    trace_formation_ratio(enemy, &model.rules),
+                ),
+                row(
+                    "修正後フォーメーション",
+                    ally.formation_modified.to_string(),
+                    enemy.formation_modified.to_string(),
+                ),
+                row(
+                    "フォーメーション係数",
+                    ally.attack_coef.to_string(),
+                    enemy.attack_coef.to_string(),
+                ),
+                row(
+                    "攻撃力 (能力補正前)",
+                    ally.attack_raw.to_string(),
+                    enemy.attack_raw.to_string(),
+                ),
+                row("有効な能力", trace_abilities(ally), trace_abilities(enemy),),
+                row(
+                    "攻撃力 (能力補正後)",
+                    ally.attack.to_string(),
+                    enemy.attack.to_string(),
+                ),
+                row(
+                    "1 個艦隊あたりのダメージ",
+                    trace_damage_per_fleet(ally),
+                    trace_damage_per_fleet(enemy),
+                ),
+            ],
+        ],
+        view_trace_fleets(C!(CLASS_HEADER_ALLY), "味方", ally),
+        view_trace_fleets(C!(CLASS_HEADER_ENEMY), "敵", enemy),
+    ])
+}
+
+fn trace_abilities(side: &TraceSide) -> String {
+    if side.abilities_effective.is_empty() {
+        "なし".to_owned()
+    } else {
+        join_abilities(&side.abilities_effective)
+    }
+}
+
+fn trace_formation_ratio(side: &TraceSide, rules: &RuleSet) -> String {
+    let forced = if side.formation_is_forced {
+        format!(
+            " ({} 以下: フォーメーション 0 に修正)",
+            rules.formation_ratio_forced()
+        )
+    } else {
+        String::new()
+    };
+
+    format!("{}{forced}", side.formation_ratio)
+}
+
+fn trace_damage_per_fleet(side: &TraceSide) -> String {
+    format!(
+        "{} / {} = {} → {}",
+        side.damage_numer, side.damage_denom, side.damage_per_fleet_uncapped, side.damage_per_fleet
+    )
+}
+
+fn view_trace_fleets(class: Attrs, header: &str, side: &TraceSide) -> Node<Msg> {
+    let fleets = side
+        .guard
+        .iter()
+        .map(|fleet| ("駐留".to_owned(), fleet))
+        .chain(
+            side.fleets
+                .iter()
+                .enumerate()
+                .map(|(i, fleet)| ((i + 1).to_string(), fleet)),
+        );
+
+    let rows = fleets.map(|(name, fleet)| {
+        tr![
+            th![name],
+            td![fleet.force.to_string()],
+            td![fleet.force_subtracted],
+            td![format!(
+                "{} ({} 以下)",
+                if fleet.is_annihilated { "全滅" } else { "-" },
+                fleet.annihilation
+            )],
+            td![view_output_fleet_force(fleet.force_damaged)],
+        ]
+    });
+
+    table![
+        thead![tr![
+            th![class, header],
+            th!["戦闘前"],
+            th!["ダメージ適用"],
+            th!["全滅判定"],
+            th!["戦闘後"],
+        ]],
+        tbody![rows],
+    ]
+}
+
+fn view_rounds(model: &Model) -> Node<Msg> {
+    div![
+        h2![format!("連続戦闘 (最大 {ROUND_MAX} ラウンド)")],
+        view_rounds_body(model),
+    ]
+}
+
+fn view_rounds_body(model: &Model) -> Option<Node<Msg>> {
+    let rounds = battle_simulate_rounds(&model.query, &model.rules, ROUND_MAX, |_| false)?;
+
+    let rows = rounds.reports().iter().enumerate().map(|(i, report)| {
+        tr![
+            td![i + 1],
+            td![report.ally_formation().to_string()],
+            td![report.ally_damage_per_fleet()],
+            td![report.ally_force_total()],
+            td![report.enemy_formation().to_string()],
+            td![report.enemy_damage_per_fleet()],
+            td![report.enemy_force_total()],
+        ]
+    });
+
+    let end = rounds_end_name(rounds.end());
+
+    Some(div![
+        table![
+            thead![
+                tr![
+                    th![attrs! { At::RowSpan => 2 }, "ラウンド"],
+                    th![C!(CLASS_HEADER_ALLY), attrs! { At::ColSpan => 3 }, "味方"],
+                    th![C!(CLASS_HEADER_ENEMY), attrs! { At::ColSpan => 3 }, "敵"],
+                ],
+                tr![
+                    th!["修正後フォーメーション"],
+                    th!["1 個艦隊あたりのダメージ"],
+                    th!["総兵力"],
+                    th!["修正後フォーメーション"],
+                    th!["1 個艦隊あたりのダメージ"],
+                    th!["総兵力"],
+                ],
+            ],
+            tbody![rows],
+        ],
+        p![format!("結果: {end}")],
+    ])
+}
+
+fn rounds_end_name(end: RoundsEnd) -> &'static str {
+    match end {
+        RoundsEnd::AllyAnnihilated => "味方全滅",
+        RoundsEnd::EnemyAnnihilated => "敵全滅",
+        RoundsEnd::BothAnnihilated => "両軍全滅",
+        RoundsEnd::RoundMax | RoundsEnd::Stopped => "決着つかず",
+    }
+}
+
+/// 疲労フラグに対応する疲労度を返す。
+fn fatigue_from_is_tired(is_tired: bool) -> Fatigue {
+    if is_tired {
+        Fatigue::TIRED
+    } else {
+        Fatigue::zero()
+    }
+}
+
+fn view_campaign(model: &Model) -> Node<Msg> {
+    div![
+        h2![format!("疲労度の推移 (1 戦闘あたり最大 {ROUND_MAX} ラウンド)")],
+        p!["戦闘ごとに入力の敵と戦い、味方の兵力と疲労度は引き継ぐ。疲労度が 80 以上の艦隊は攻撃に参加しない。"],
+        view_campaign_input(model),
+        view_campaign_body(model),
+    ]
+}
+
+fn view_campaign_input(model: &Model) -> Node<Msg> {
+    let rule = model.fatigue_rule;
+
+    let cols_header = (0..ALLY_FLEET_COUNT).map(|i| th![i + 1]);
+
+    let cols_fatigue = (0..ALLY_FLEET_COUNT).map(|i| {
+        let on_change = move |fatigue| Msg::SetAllyFleetFatigue(i, fatigue);
+        td![view_input_number(
+            model,
+            InputField::AllyFleetFatigue(i),
+            model.ally_fleet_fatigues[i],
+            on_change,
+        )]
+    });
+
+    div![
+        table![
+            thead![tr![th![], cols_header]],
+            tbody![tr![th!["疲労度"], cols_fatigue]],
+        ],
+        p![
+            "戦闘回数: ",
+            view_input_campaign_battle_count(model),
+            " 1 ラウンドごとの上昇: ",
+            view_input_number(
+                model,
+                InputField::FatigueGainPerRound,
+                rule.gain_per_round(),
+                Msg::SetFatigueGainPerRound,
+            ),
+            " 1 ターンごとの回復: ",
+            view_input_number(
+                model,
+                InputField::FatigueRecoveryPerTurn,
+                rule.recovery_per_turn(),
+                Msg::SetFatigueRecoveryPerTurn,
+            ),
+            " 戦闘間のターン数: ",
+            view_input_number(
+                model,
+                InputField::FatigueRestTurns,
+                rule.rest_turns(),
+                Msg::SetFatigueRestTurns,
+            ),
+        ],
+    ]
+}
+
+fn view_input_campaign_battle_count(model: &Model) -> Vec<Node<Msg>> {
+    let field = InputField::CampaignBattleCount;
+    let invalid = model.invalid_inputs.get(&field);
+
+    // 不正な入力はそのまま表示し続ける。
+    let text = invalid.map_or_else(
+        || model.campaign_battle_count.to_string(),
+        |invalid| invalid.text.clone(),
+    );
+
+    let parse = |s: &str| {
+        let count: usize = s.parse()?;
+        anyhow::ensure!(
+            count <= CAMPAIGN_BATTLE_COUNT_MAX,
+            "battle count should be at most {CAMPAIGN_BATTLE_COUNT_MAX}: {count}"
+        );
+        Ok(count)
+    };
+
+    vec![
+        input![
+            C!(
+                CLASS_INPUT_NUMBER,
+                IF!(invalid.is_some() => CLASS_INPUT_INVALID),
+            ),
+            attrs! {
+                At::Type => "number",
+                At::Min => 0,
+                At::Max => CAMPAIGN_BATTLE_COUNT_MAX,
+                At::Value => text,
+            },
+            input_ev(Ev::Change, move |s| {
+                parse_input_with(field, s, parse, Msg::SetCampaignBattleCount)
+            }),
+        ],
+        view_input_error(invalid),
+    ]
+}
+
+fn view_campaign_body(model: &Model) -> Option<Node<Msg>> {
+    let campaign = campaign_simulate(
+        &model.query,
+        &model.rules,
+        &model.ally_fleet_fatigues,
+        &model.fatigue_rule,
+        model.campaign_battle_count,
+        ROUND_MAX,
+    )?;
+
+    let cols_header = (0..ALLY_FLEET_COUNT).map(|i| th![i + 1]);
+
+    let rows = campaign
+        .battles()
+        .iter()
+        .enumerate()
+        .flat_map(|(i_battle, battle)| {
+            let round_count = battle.rounds().len();
+            battle
+                .rounds()
+                .iter()
+                .enumerate()
+                .map(move |(i_round, round)| {
+                    let cols_fatigue = (0..ALLY_FLEET_COUNT).map(|i| {
+                        if round.query().ally_fleet_force(i).is_zero() {
+                            td!["-"]
+                        } else {
+                            td![
+                                C!(IF!(round.ally_fleet_is_tired(i) => CLASS_OUTPUT_FATIGUE_TIRED)),
+                                round.ally_fleet_fatigue(i).to_string(),
+                            ]
+                        }
+                    });
+                    let end = (i_round + 1 == round_count).then(|| rounds_end_name(battle.end()));
+
+                    tr![
+                        td![i_battle + 1],
+                        td![i_round + 1],
+                        cols_fatigue,
+                        td![round.report().ally_force_total()],
+                        td![round.report().enemy_force_total()],
+                        td![end],
+                    ]
+                })
+        });
+
+    let dropouts = (0..ALLY_FLEET_COUNT)
+        .filter(|&i| !model.query.ally_fleet_force(i).is_zero())
+        .map(|i| {
+            let text = match campaign.ally_fleet_dropout(i) {
+                Some((i_battle, i_round)) => format!(
+                    "第 {} 戦のラウンド {} から攻撃に参加しない",
+                    i_battle + 1,
+                    i_round + 1
+                ),
+                None => "疲労により脱落しない".to_owned(),
+            };
+            li![format!("艦隊 {}: {text}", i + 1)]
+        });
+
+    Some(div![
+        table![
+            thead![
+                tr![
+                    th![attrs! { At::RowSpan => 2 }, "戦闘"],
+                    th![attrs! { At::RowSpan => 2 }, "ラウンド"],
+                    th![
+                        C!(CLASS_HEADER_ALLY),
+                        attrs! { At::ColSpan => ALLY_FLEET_COUNT },
+                        "味方の疲労度 (ラウンド開始時)"
+                    ],
+                    th![
+                        C!(CLASS_HEADER_ALLY),
+                        attrs! { At::RowSpan => 2 },
+                        "味方総兵力"
+                    ],
+                    th![
+                        C!(CLASS_HEADER_ENEMY),
+                        attrs! { At::RowSpan => 2 },
+                        "敵総兵力"
+                    ],
+                    th![attrs! { At::RowSpan => 2 }, "結果"],
+                ],
+                tr![cols_header],
+            ],
+            tbody![rows],
+        ],
+        ul![dropouts],
+    ])
+}
+
+fn view_coef(model: &Model) -> Node<Msg> {
+    let cols_header =
+        Formation::all().map(|formation| th![C!(CLASS_HEADER_ENEMY), formation.to_string()]);
+
+    let rows = Formation::all().map(|ally_formation| {
+        let is_current_ally = ally_formation == model.query.ally_formation();
+
+        let cols = Formation::all().map(|enemy_formation| {
+            let is_current = is_current_ally && enemy_formation == model.query.enemy_formation();
+            let coef = model.rules.attack_coef(ally_formation, enemy_formation);
+
+            let mut query = model.query.clone();
+            query.set_ally_formation(ally_formation);
+            query.set_enemy_formation(enemy_formation);
+            let damage = battle_simulate(&query, &model.rules).map_or_else(
+                || "-".to_owned(),
+                |report| report.enemy_damage_per_fleet().to_string(),
+            );
+
+            td![
+                C!(
+                    CLASS_MATRIX_CLICKABLE,
+                    IF!(is_current => CLASS_MATRIX_CURRENT),
+                ),
+                format!("{coef} ({damage})"),
+                ev(Ev::Click, move |_| Msg::SetFormations(
+                    ally_formation,
+                    enemy_formation
+                )),
+            ]
+        });
+
+        tr![th![C!(CLASS_HEADER_ALLY), ally_formation.to_string()], cols]
+    });
+
+    div![
+        h2!["フォーメーション係数表"],
+        p!["行: 味方フォーメーション, 列: 敵フォーメーション。各マスは味方のフォーメーション係数 (敵の 1 個艦隊あたりのダメージ)。マスをクリックすると両軍のフォーメーションを設定する。"],
+        table![thead![tr![th![], cols_header]], tbody![rows]],
+    ]
+}
+
+fn view_matrix(model: &Model) -> Node<Msg> {
+    div![
+        h2!["フォーメーション相性表"],
+        p![format!(
+            "評価基準: {} (行: 味方フォーメーション, 列: 敵フォーメーション)",
+            objective_name(model.objective)
+        )],
+        view_matrix_body(model),
+    ]
+}
+
+fn view_matrix_body(model: &Model) -> Node<Msg> {
+    let cols_header =
+        Formation::all().map(|formation| th![C!(CLASS_HEADER_ENEMY), formation.to_string()]);
+
+    // 出現比の入力欄は、表を計算できない場合も値を直せるよう常に表示する。
+    let cols_prob = Formation::all().map(|formation| {
+        let field = InputField::EnemyFormationProb(formation);
+        let invalid = model.invalid_inputs.get(&field);
+        let text = invalid.map_or_else(
+            || model.enemy_formation_probs[formation.index()].to_string(),
+            |invalid| invalid.text.clone(),
+        );
+        let on_change = move |prob| Msg::SetEnemyFormationProb(formation, prob);
+
+        td![
+            input![
+                C!(
+                    CLASS_INPUT_PROB,
+                    IF!(invalid.is_some() => CLASS_INPUT_INVALID),
+                ),
+                attrs! {
+                    At::Type => "number",
+                    At::Min => 0,
+                    At::Value => text,
+                },
+                input_ev(Ev::Change, move |s| parse_input(field, s, on_change)),
+            ],
+            view_input_error(invalid),
+        ]
+    });
+    let row_prob = tr![th!["敵の出現比"], cols_prob, td![], td![], td![]];
+
+    let enemy_probs = model.enemy_formation_probs.map(f64::from);
+    let Some(matrix) = analyze_formations(
+        &model.query,
+        &model.rules,
+        model.objective,
+        Some(&enemy_probs),
+    ) else {
+        let error = match model.query.validate() {
+            Err(e) => format!("計算できません: {e}"),
+            Ok(()) => "計算できません: 敵の出現比のいずれかを正にしてください".to_owned(),
+        };
+        return div![
+            p![C!(CLASS_INPUT_ERROR), error],
+            table![
+                thead![tr![th![], cols_header, th![], th![], th![]]],
+                tbody![row_prob],
+            ],
+        ];
+    };
+
+    let minimax = matrix.minimax_formation();
+    let expected = matrix.expected_formation();
+
+    let rows = Formation::all().map(|ally_formation| {
+        let is_current_ally = ally_formation == model.query.ally_formation();
+
+        let cols = Formation::all().map(|enemy_formation| {
+            let outcome = matrix.outcome(ally_formation, enemy_formation);
+            let is_current = is_current_ally && enemy_formation == model.query.enemy_formation();
+            td![
+                C!(IF!(is_current => CLASS_MATRIX_CURRENT)),
+                outcome.value(matrix.objective()),
+            ]
+        });
+
+        let summary = matrix.summary(ally_formation);
+
+        tr![
+            th![C!(CLASS_HEADER_ALLY), ally_formation.to_string()],
+            cols,
+            td![
+                C!(IF!(ally_formation == minimax => CLASS_MATRIX_BEST)),
+                format!(
+                    "{} (敵 {})",
+                    summary.worst(),
+                    summary.worst_enemy_formation()
+                ),
+            ],
+            td![format!(
+                "{} (敵 {})",
+                summary.best(),
+                summary.best_enemy_formation()
+            )],
+            td![
+                C!(IF!(ally_formation == expected => CLASS_MATRIX_BEST)),
+                format!("{:.1}", summary.expected()),
+            ],
+        ]
+    });
+
+    div![
+        table![
+            thead![tr![th![], cols_header, th!["最悪"], th!["最良"], th!["期待値"]]],
+            tbody![rows, row_prob],
+        ],
+        p![format!(
+            "最悪の場合に最も良い味方フォーメーション: {minimax}, 期待値が最も良い味方フォーメーション: {expected}"
+        )],
+    ]
+}
+
+fn view_solve(model: &Model) -> Node<Msg> {
+    let results = model
+        .solve_results
+        .as_ref()
+        .filter(|results| results.query == model.query);
+
+    let body = match results {
+        Some(results) => view_solve_table(results),
+        None => {
+            let note = model
+                .solve_results
+                .is_some()
+                .then(|| span![" 入力が変わったので再計算が必要。"]);
+            p![
+                button!["計算する", ev(Ev::Click, |_| Msg::SolveMinAllyForce)],
+                note,
+            ]
+        }
+    };
+
+    div![
+        h2!["敵全滅に必要な味方の最小兵力"],
+        p!["敵と味方フォーメーションは入力値を使い、味方は全艦隊が攻撃に参加するものとする。"],
+        p!["兵力は各艦隊に均等に配分した場合だけを調べる。2 ラウンド以上では、配分を変えればより少ない総兵力で全滅させられる場合がある。"],
+        body,
+    ]
+}
+
+fn view_solve_table(results: &SolveResults) -> Node<Msg> {
+    let cols_header = (0..ALLY_FLEET_COUNT).map(|i| th![i + 1]);
+
+    let rows = results.solutions.iter().enumerate().map(|(i, solution)| {
+        let round_count = i + 1;
+
+        let cols = match solution {
+            Some(solution) => {
+                let cols_force = (0..ALLY_FLEET_COUNT).map(|i| {
+                    let value = solution.query().ally_fleet_force(i);
+                    td![view_output_fleet_force(value)]
+                });
+                [td![solution.force_total()], td![solution.round_count()]]
+                    .into_iter()
+                    .chain(cols_force)
+                    .collect()
+            }
+            None => vec![td![
+                attrs! { At::ColSpan => ALLY_FLEET_COUNT + 2 },
+                "全滅させられない",
+            ]],
+        };
+
+        tr![th![format!("{round_count} ラウンド以内")], cols]
+    });
+
+    table![
+        thead![tr![
+            th![],
+            th!["総兵力"],
+            th!["所要ラウンド数"],
+            cols_header
+        ]],
+        tbody![rows],
+    ]
+}
+
+fn view_sensitivity(model: &Model) -> Node<Msg> {
+    const ID_SELECT: &str = "select-sweep-target";
+
+    let options = SweepTarget::all().enumerate().map(|(i, target)| {
+        option![
+            attrs! {
+                At::Value => i,
+                At::Selected => (target == model.sweep_target).as_at_value(),
+            },
+            sweep_target_name(target),
+        ]
+    });
+
+    let sweep = sweep_fleet_force(&model.query, &model.rules, model.sweep_target);
+
+    div![
+        h2!["兵力とダメージの関係"],
+        p!["1 個艦隊の兵力だけを 0 から 100 まで変えたとき、1 艦隊あたりのダメージが変わる兵力 (ブレークポイント) を示す。"],
+        p![
+            label![
+                attrs! {
+                    At::For => ID_SELECT,
+                },
+                "兵力を変える艦隊: ",
+            ],
+            select![
+                id!(ID_SELECT),
+                options,
+                input_ev(Ev::Change, |s| {
+                    let i: usize = s.parse().ok()?;
+                    SweepTarget::all().nth(i).map(Msg::SetSweepTarget)
+                }),
+            ],
+        ],
+        view_sensitivity_summary(&sweep),
+        view_sensitivity_chart(&sweep),
+        view_sensitivity_breakpoints(&sweep),
+    ]
+}
+
+fn sweep_target_name(target: SweepTarget) -> String {
+    match target {
+        SweepTarget::AllyFleet(idx) => format!("味方艦隊{}", idx + 1),
+        SweepTarget::EnemyGuard => "敵駐留艦隊".to_owned(),
+        SweepTarget::EnemyFleet(idx) => format!("敵艦隊{}", idx + 1),
+    }
+}
+
+fn sweep_set_force_msg(target: SweepTarget, force: FleetForce) -> Msg {
+    match target {
+        SweepTarget::AllyFleet(idx) => Msg::SetAllyFleetForce(idx, force),
+        SweepTarget::EnemyGuard => Msg::SetEnemyGuardForce(force),
+        SweepTarget::EnemyFleet(idx) => Msg::SetEnemyFleetForce(idx, force),
+    }
+}
+
+fn view_sensitivity_summary(sweep: &Sweep) -> Node<Msg> {
+    let current = sweep.current().inner();
+
+    let next = match sweep.next_breakpoint() {
+        Some(point) => format!(
+            "あと {} 増やすと変わる (兵力 {})",
+            point.force().inner() - current,
+            point.force()
+        ),
+        None => "増やしても変わらない".to_owned(),
+    };
+    let prev = match sweep.prev_breakpoint() {
+        Some(point) => format!(
+            "{} 減らすと変わる (兵力 {})",
+            current - point.force().inner() + 1,
+            point.force().inner() - 1
+        ),
+        None => "減らしても変わらない".to_owned(),
+    };
+
+    p![format!("現在の兵力 {current}: {next}、{prev}。")]
+}
+
+fn view_sensitivity_chart(sweep: &Sweep) -> Node<Msg> {
+    const X_SCALE: u32 = 4;
+    const HEIGHT: u32 = 120;
+
+    let width = X_SCALE * (FleetForce::MAX.inner() + 1);
+    let y_max = sweep
+        .points()
+        .iter()
+        .filter_map(SweepPoint::damages_per_fleet)
+        .map(|(enemy, ally)| enemy.max(ally))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    // 値を階段状の折れ線にする。戦闘できない兵力では線を切る。
+    let series = |class: &'static str, value: fn((u32, u32)) -> u32| {
+        let mut lines = Vec::<Vec<String>>::new();
+        let mut line = Vec::<String>::new();
+        for point in sweep.points() {
+            match point.damages_per_fleet() {
+                Some(damages) => {
+                    let x = X_SCALE * point.force().inner();
+                    let y = HEIGHT - HEIGHT * value(damages) / y_max;
+                    line.push(format!("{x},{y}"));
+                    line.push(format!("{},{y}", x + X_SCALE));
+                }
+                None => lines.push(std::mem::take(&mut line)),
+            }
+        }
+        lines.push(line);
+
+        lines
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .map(move |line| {
+                polyline![
+                    C!(class),
+                    attrs! {
+                        At::Points => line.join(" "),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let x_current = X_SCALE * sweep.current().inner() + X_SCALE / 2;
+
+    div![
+        svg![
+            attrs! {
+                At::Width => width,
+                At::Height => HEIGHT,
+                At::ViewBox => format!("0 0 {width} {HEIGHT}"),
+            },
+            series(CLASS_SWEEP_ENEMY, |(enemy, _)| enemy),
+            series(CLASS_SWEEP_ALLY, |(_, ally)| ally),
+            line_![
+                C!(CLASS_SWEEP_CURRENT),
+                attrs! {
+                    At::X1 => x_current,
+                    At::Y1 => 0,
+                    At::X2 => x_current,
+                    At::Y2 => HEIGHT,
+                },
+            ],
+        ],
+        p![
+            span![C!(CLASS_HEADER_ENEMY), "敵の 1 艦隊あたりのダメージ"],
+            " ",
+            span![C!(CLASS_HEADER_ALLY), "味方の 1 艦隊あたりのダメージ"],
+            format!(" (縦軸の最大 {y_max}、縦線は現在の兵力)"),
+        ],
+    ]
+}
+
+fn view_sensitivity_breakpoints(sweep: &Sweep) -> Node<Msg> {
+    let target = sweep.target();
+    let current = sweep.prev_breakpoint().map(SweepPoint::force);
+
+    let rows = sweep.breakpoints().map(|point| {
+        let force = point.force();
+        let cols = match point.outcome() {
+            Some(outcome) => vec![
+                td![outcome.report().enemy_damage_per_fleet()],
+                td![outcome.report().ally_damage_per_fleet()],
+                td![outcome.enemy_damage()],
+                td![outcome.ally_loss()],
+            ],
+            None => vec![td![attrs! { At::ColSpan => 4 }, "戦闘できません"]],
+        };
+
+        tr![
+            C!(IF!(Some(force) == current => CLASS_SWEEP_STEP_CURRENT)),
+            td![force.to_string()],
+            cols,
+            td![button![
+                "この兵力にする",
+                ev(Ev::Click, move |_| sweep_set_force_msg(target, force)),
+            ]],
+        ]
+    });
+
+    table![
+        thead![tr![
+            th!["兵力"],
+            th!["敵の 1 艦隊あたりのダメージ"],
+            th!["味方の 1 艦隊あたりのダメージ"],
+            th!["敵の損害"],
+            th!["味方の損害"],
+            th![],
+        ]],
+        tbody![rows],
+    ]
+}
+
+fn view_allocate(model: &Model) -> Node<Msg> {
+    div![
+        h2!["休ませる艦隊の選択"],
+        p!["兵力のうち 100 未満の端数は攻撃力に寄与しない。疲労していない味方艦隊を全て参加させた場合と同じ敵の損害を、最も少ない艦隊数で出す組み合わせを示す。"],
+        view_allocate_body(model),
+    ]
+}
+
+fn view_allocate_body(model: &Model) -> Node<Msg> {
+    let Some(allocation) = optimize_ally_rest(&model.query, &model.rules) else {
+        return p!["参加できる艦隊がない"];
+    };
+
+    let fleet_list = |idxs: &[usize]| {
+        if idxs.is_empty() {
+            "なし".to_owned()
+        } else {
+            idxs.iter()
+                .map(|i| (i + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    };
+
+    let rested = allocation.rested_fleets().to_vec();
+    let outcome = allocation.outcome();
+
+    div![
+        table![
+            thead![tr![th![], th!["全艦隊参加"], th!["最適化後"]]],
+            tbody![
+                tr![
+                    th!["参加艦隊"],
+                    td![fleet_list(allocation.available_fleets())],
+                    td![fleet_list(allocation.committed_fleets())],
+                ],
+                tr![
+                    th!["敵の損害"],
+                    td![allocation.baseline_enemy_damage()],
+                    td![outcome.enemy_damage()],
+                ],
+                tr![
+                    th!["味方の損害"],
+                    td![allocation.baseline_ally_loss()],
+                    td![outcome.ally_loss()],
+                ],
+                tr![
+                    th!["修正後フォーメーション"],
+                    td![allocation.baseline_ally_formation().to_string()],
+                    td![outcome.report().ally_formation().to_string()],
+                ],
+            ],
+        ],
+        p![
+            format!("休ませる艦隊: {}", fleet_list(&rested)),
+            " ",
+            button![
+                "休ませる",
+                attrs! {
+                    At::Disabled => rested.is_empty().as_at_value(),
+                },
+                ev(Ev::Click, move |_| Msg::RestAllyFleets(rested)),
+            ],
+        ],
+    ]
+}
+
+fn view_input_formation<F>(
+    model: &Model,
+    id: &str,
+    field: InputField,
+    value: Formation,
+    on_change: F,
+) -> Node<Msg>
 where
     F: FnOnce(Formation) -> Msg + Clone + 'static,
 {
+    let invalid = model.invalid_inputs.get(&field);
+
     p![
         label![
             attrs! {
@@ -252,41 +2121,215 @@ where
             },
             "フォーメーション: ",
         ],
-        input![
+        select![
             id!(id),
-            C!(CLASS_INPUT_FORMATION),
+            C!(
+                CLASS_INPUT_FORMATION,
+                IF!(invalid.is_some() => CLASS_INPUT_INVALID),
+            ),
+            Formation::all().map(|formation| {
+                option![
+                    attrs! {
+                        At::Value => formation,
+                        At::Selected => (formation == value).as_at_value(),
+                    },
+                    formation_label(formation),
+                ]
+            }),
+            input_ev(Ev::Change, move |s| parse_input(field, s, on_change)),
+        ],
+        view_formation_note(model, value),
+        view_input_error(invalid),
+    ]
+}
+
+/// フォーメーションの番号と名前 (あれば)、崩壊フォーメーションならその旨を並べた表示を返す。
+fn formation_label(formation: Formation) -> String {
+    let name = formation
+        .name_ja()
+        .map_or_else(String::new, |name| format!(": {name}"));
+
+    if formation.is_broken() {
+        format!("{formation}{name} (兵力不足時に強制)")
+    } else {
+        format!("{formation}{name}")
+    }
+}
+
+/// フォーメーションの説明と、現在の規則でヤンの補正を受けないならその旨を並べた表示を返す。
+fn view_formation_note(model: &Model, formation: Formation) -> Option<Node<Msg>> {
+    let notes: Vec<&str> = formation
+        .description()
+        .into_iter()
+        .chain(
+            model
+                .rules
+                .yang()
+                .is_exempt(formation)
+                .then_some("相手のヤンの補正を受けない"),
+        )
+        .collect();
+
+    (!notes.is_empty()).then(|| span![format!(" ({})", notes.join("。"))])
+}
+
+fn view_input_fleet_force<F>(
+    model: &Model,
+    field: InputField,
+    value: FleetForce,
+    on_change: F,
+) -> Vec<Node<Msg>>
+where
+    F: FnOnce(FleetForce) -> Msg + Clone + 'static,
+{
+    let invalid = model.invalid_inputs.get(&field);
+
+    // 不正な入力はそのまま表示し続ける。
+    let text = invalid.map_or_else(|| value.to_string(), |invalid| invalid.text.clone());
+
+    vec![
+        input![
+            C!(
+                CLASS_INPUT_FLEET_FORCE,
+                IF!(value.is_zero() => CLASS_INPUT_FLEET_FORCE_DEAD),
+                IF!(invalid.is_some() => CLASS_INPUT_INVALID),
+            ),
             attrs! {
                 At::Type => "number",
-                At::Min => Formation::MIN,
-                At::Max => Formation::MAX,
-                At::Value => value,
+                At::Min => FleetForce::MIN,
+                At::Max => FleetForce::MAX,
+                At::Value => text,
             },
-            input_ev(Ev::Change, |s| s.parse::<Formation>().ok().map(on_change)),
+            input_ev(Ev::Change, move |s| parse_input(field, s, on_change)),
         ],
+        view_input_error(invalid),
     ]
 }
 
-fn view_input_fleet_force<F>(value: FleetForce, on_change: F) -> Node<Msg>
+/// 入力欄の文字列を解釈し、成功すれば on_change のメッセージを、失敗すればエラーを記録する
+/// メッセージを返す。
+fn parse_input<T, F>(field: InputField, text: String, on_change: F) -> Msg
 where
-    F: FnOnce(FleetForce) -> Msg + Clone + 'static,
+    T: std::str::FromStr,
+    T::Err: Into<anyhow::Error>,
+    F: FnOnce(T) -> Msg,
 {
-    input![
-        C!(
-            CLASS_INPUT_FLEET_FORCE,
-            IF!(value.is_zero() => CLASS_INPUT_FLEET_FORCE_DEAD),
+    parse_input_with(field, text, |s| s.parse().map_err(Into::into), on_change)
+}
+
+/// `parse_input()` の解釈を parse で行うもの。
+fn parse_input_with<T, P, F>(field: InputField, text: String, parse: P, on_change: F) -> Msg
+where
+    P: FnOnce(&str) -> anyhow::Result<T>,
+    F: FnOnce(T) -> Msg,
+{
+    match parse(&text) {
+        Ok(value) => on_change(value),
+        Err(e) => Msg::SetInvalidInput(
+            field,
+            InvalidInput {
+                text,
+                error: format!("{e:#}"),
+            },
         ),
-        attrs! {
-            At::Type => "number",
-            At::Min => FleetForce::MIN,
-            At::Max => FleetForce::MAX,
-            At::Value => value,
-        },
-        input_ev(Ev::Change, |s| s.parse::<FleetForce>().ok().map(on_change)),
+    }
+}
+
+fn view_input_error(invalid: Option<&InvalidInput>) -> Node<Msg> {
+    match invalid {
+        Some(invalid) => div![C!(CLASS_INPUT_ERROR), &invalid.error],
+        None => empty![],
+    }
+}
+
+fn view_input_number<T, F>(
+    model: &Model,
+    field: InputField,
+    value: T,
+    on_change: F,
+) -> Vec<Node<Msg>>
+where
+    T: std::str::FromStr + std::fmt::Display + 'static,
+    T::Err: Into<anyhow::Error>,
+    F: FnOnce(T) -> Msg + Clone + 'static,
+{
+    let invalid = model.invalid_inputs.get(&field);
+
+    // 不正な入力はそのまま表示し続ける。
+    let text = invalid.map_or_else(|| value.to_string(), |invalid| invalid.text.clone());
+
+    vec![
+        input![
+            C!(
+                CLASS_INPUT_NUMBER,
+                IF!(invalid.is_some() => CLASS_INPUT_INVALID),
+            ),
+            attrs! {
+                At::Type => "number",
+                At::Min => 0,
+                At::Value => text,
+            },
+            input_ev(Ev::Change, move |s| parse_input(field, s, on_change)),
+        ],
+        view_input_error(invalid),
     ]
 }
 
+/// 指揮官の能力を空白区切りで入力する。
+fn view_input_abilities<F>(
+    model: &Model,
+    id: &str,
+    field: InputField,
+    value: &[Ability],
+    on_change: F,
+) -> Node<Msg>
+where
+    F: FnOnce(Vec<Ability>) -> Msg + Clone + 'static,
+{
+    let invalid = model.invalid_inputs.get(&field);
+
+    // 不正な入力はそのまま表示し続ける。
+    let text = invalid.map_or_else(|| join_abilities(value), |invalid| invalid.text.clone());
+
+    p![
+        label![
+            attrs! {
+                At::For => id,
+            },
+            "指揮官の能力 (yang または 攻撃加算:防御除数:防御加算[:無効フォーメーション], 空白区切り): ",
+        ],
+        input![
+            id!(id),
+            C!(
+                CLASS_INPUT_ABILITIES,
+                IF!(invalid.is_some() => CLASS_INPUT_INVALID),
+            ),
+            attrs! {
+                At::Type => "text",
+                At::Value => text,
+            },
+            input_ev(Ev::Change, move |s| {
+                let parse = |s: &str| s.split_whitespace().map(str::parse).collect();
+                parse_input_with(field, s, parse, on_change)
+            }),
+        ],
+        view_input_error(invalid),
+    ]
+}
+
+fn join_abilities(abilities: &[Ability]) -> String {
+    abilities
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn view_output_formation(value: Formation) -> Node<Msg> {
-    p![format!("修正後フォーメーション: {value}")]
+    p![format!(
+        "修正後フォーメーション: {}",
+        formation_label(value)
+    )]
 }
 
 fn view_output_damage_per_fleet(value: u32) -> Node<Msg> {
@@ -0,0 +1,179 @@
+//! 入力 (query) の編集履歴。元に戻す・やり直すに使う。
+
+use crate::battle::*;
+
+/// 保持する編集の最大数。これを超えたら古いものから捨てる。
+const HISTORY_MAX: usize = 100;
+
+/// query の編集履歴。
+#[derive(Debug, Default)]
+pub(super) struct History {
+    /// 適用済みの編集 (古い順)。
+    done: Vec<Edit>,
+    /// 元に戻した編集 (最後のものが次にやり直される)。
+    undone: Vec<Edit>,
+}
+
+impl History {
+    /// query が before から after に変わったことを記録する。変わっていなければ何もしない。
+    ///
+    /// 新たに編集したので、元に戻した編集はやり直せなくなる。
+    pub(super) fn record(&mut self, before: &Query, after: &Query) {
+        if before == after {
+            return;
+        }
+
+        self.done.push(Edit::new(before.clone(), after.clone()));
+        if self.done.len() > HISTORY_MAX {
+            self.done.remove(0);
+        }
+        self.undone.clear();
+    }
+
+    /// 最後の編集を取り消し、編集前の query を返す。取り消せる編集がなければ `None` を返す。
+    pub(super) fn undo(&mut self) -> Option<Query> {
+        let edit = self.done.pop()?;
+        let query = edit.before.clone();
+        self.undone.push(edit);
+
+        Some(query)
+    }
+
+    /// 最後に取り消した編集をやり直し、編集後の query を返す。やり直せる編集がなければ
+    /// `None` を返す。
+    pub(super) fn redo(&mut self) -> Option<Query> {
+        let edit = self.undone.pop()?;
+        let query = edit.after.clone();
+        self.done.push(edit);
+
+        Some(query)
+    }
+
+    pub(super) fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub(super) fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// 適用済みの編集を古い順に返す。
+    pub(super) fn done(&self) -> &[Edit] {
+        &self.done
+    }
+
+    /// 元に戻した編集を、やり直される順に返す。
+    pub(super) fn undone(&self) -> impl Iterator<Item = &Edit> {
+        self.undone.iter().rev()
+    }
+}
+
+/// 1 回の編集。
+#[derive(Debug)]
+pub(super) struct Edit {
+    before: Query,
+    after: Query,
+    changes: Vec<String>,
+}
+
+impl Edit {
+    fn new(before: Query, after: Query) -> Self {
+        let changes = describe_changes(&before, &after);
+
+        Self {
+            before,
+            after,
+            changes,
+        }
+    }
+
+    /// 変更内容の説明 (例: `味方艦隊3 兵力: 100 → 60`) を返す。
+    pub(super) fn description(&self) -> String {
+        self.changes.join(", ")
+    }
+}
+
+/// before と after の違いを項目ごとに説明する。
+fn describe_changes(before: &Query, after: &Query) -> Vec<String> {
+    let mut changes = Vec::<String>::new();
+
+    let mut push = |label: String, old: String, new: String| {
+        if old != new {
+            changes.push(format!("{label}: {old} → {new}"));
+        }
+    };
+
+    for i in 0..ALLY_FLEET_COUNT {
+        push(
+            format!("味方艦隊{} 兵力", i + 1),
+            before.ally_fleet_force(i).to_string(),
+            after.ally_fleet_force(i).to_string(),
+        );
+        push(
+            format!("味方艦隊{} 疲労", i + 1),
+            tired_label(before.ally_fleet_is_tired(i)),
+            tired_label(after.ally_fleet_is_tired(i)),
+        );
+    }
+    push(
+        "味方フォーメーション".to_owned(),
+        before.ally_formation().to_string(),
+        after.ally_formation().to_string(),
+    );
+    push(
+        "味方の能力".to_owned(),
+        abilities_label(before.ally_abilities()),
+        abilities_label(after.ally_abilities()),
+    );
+
+    push(
+        "敵駐留艦隊 兵力".to_owned(),
+        before.enemy_guard_force().to_string(),
+        after.enemy_guard_force().to_string(),
+    );
+    push(
+        "敵駐留艦隊 疲労".to_owned(),
+        tired_label(before.enemy_guard_is_tired()),
+        tired_label(after.enemy_guard_is_tired()),
+    );
+    for i in 0..ENEMY_FLEET_COUNT {
+        push(
+            format!("敵艦隊{} 兵力", i + 1),
+            before.enemy_fleet_force(i).to_string(),
+            after.enemy_fleet_force(i).to_string(),
+        );
+        push(
+            format!("敵艦隊{} 疲労", i + 1),
+            tired_label(before.enemy_fleet_is_tired(i)),
+            tired_label(after.enemy_fleet_is_tired(i)),
+        );
+    }
+    push(
+        "敵フォーメーション".to_owned(),
+        before.enemy_formation().to_string(),
+        after.enemy_formation().to_string(),
+    );
+    push(
+        "敵の能力".to_owned(),
+        abilities_label(before.enemy_abilities()),
+        abilities_label(after.enemy_abilities()),
+    );
+
+    changes
+}
+
+fn tired_label(is_tired: bool) -> String {
+    if is_tired { "あり" } else { "なし" }.to_owned()
+}
+
+fn abilities_label(abilities: &[Ability]) -> String {
+    if abilities.is_empty() {
+        return "なし".to_owned();
+    }
+
+    abilities
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
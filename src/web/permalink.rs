@@ -0,0 +1,176 @@
+use anyhow::{ensure, Context as _};
+use seed::prelude::*;
+
+use crate::battle::*;
+
+const KEY_ALLY_FLEET_FORCES: &str = "a";
+const KEY_ALLY_FLEET_TIREDS: &str = "at";
+const KEY_ALLY_FORMATION: &str = "af";
+const KEY_ALLY_ABILITIES: &str = "ab";
+const KEY_ENEMY_FLEET_FORCES: &str = "e";
+const KEY_ENEMY_FLEET_TIREDS: &str = "et";
+const KEY_ENEMY_GUARD_FORCE: &str = "g";
+const KEY_ENEMY_GUARD_IS_TIRED: &str = "gt";
+const KEY_ENEMY_FORMATION: &str = "ef";
+const KEY_ENEMY_ABILITIES: &str = "eb";
+/// 以前の URL との互換用。今は `KEY_ENEMY_ABILITIES` に含める。
+const KEY_ENEMY_HAS_YANG: &str = "y";
+
+/// query を URL のクエリ文字列に変換する。
+///
+/// 例: `?a=100,80&at=2&af=3&e=50&et=1&g=100&ef=1&gt=1&eb=yang`
+///
+/// 兵力は艦隊番号順にカンマ区切りで並べる (末尾の 0 は省略)。疲労中の艦隊は 1 始まりの番号で並べる。
+/// 指揮官の能力はカンマ区切りで並べる (能力がなければ省略)。
+pub(super) fn query_to_search(query: &Query) -> UrlSearch {
+    let ally_forces = (0..ALLY_FLEET_COUNT).map(|i| query.ally_fleet_force(i));
+    let ally_tireds = (0..ALLY_FLEET_COUNT)
+        .filter(|&i| query.ally_fleet_is_tired(i))
+        .map(|i| i + 1);
+    let enemy_forces = (0..ENEMY_FLEET_COUNT).map(|i| query.enemy_fleet_force(i));
+    let enemy_tireds = (0..ENEMY_FLEET_COUNT)
+        .filter(|&i| query.enemy_fleet_is_tired(i))
+        .map(|i| i + 1);
+
+    let mut params = vec![
+        (KEY_ALLY_FLEET_FORCES, join_forces(ally_forces)),
+        (KEY_ALLY_FLEET_TIREDS, join(ally_tireds)),
+        (KEY_ALLY_FORMATION, query.ally_formation().to_string()),
+        (KEY_ENEMY_FLEET_FORCES, join_forces(enemy_forces)),
+        (KEY_ENEMY_FLEET_TIREDS, join(enemy_tireds)),
+        (KEY_ENEMY_GUARD_FORCE, query.enemy_guard_force().to_string()),
+        (KEY_ENEMY_FORMATION, query.enemy_formation().to_string()),
+    ];
+    if query.enemy_guard_is_tired() {
+        params.push((KEY_ENEMY_GUARD_IS_TIRED, "1".to_owned()));
+    }
+    if !query.ally_abilities().is_empty() {
+        params.push((KEY_ALLY_ABILITIES, join(query.ally_abilities())));
+    }
+    if !query.enemy_abilities().is_empty() {
+        params.push((KEY_ENEMY_ABILITIES, join(query.enemy_abilities())));
+    }
+
+    UrlSearch::new(params.into_iter().map(|(key, value)| (key, [value])))
+}
+
+/// URL のクエリ文字列から query を復元する。
+///
+/// 含まれないパラメータはデフォルト値のまま。不正な値が含まれる場合はエラーを返す。
+pub(super) fn query_from_search(search: &UrlSearch) -> anyhow::Result<Query> {
+    let mut query = Query::default();
+
+    if let Some(s) = get(search, KEY_ALLY_FLEET_FORCES) {
+        let forces = split_forces(s, ALLY_FLEET_COUNT).context("invalid ally fleet forces")?;
+        for (i, force) in forces.into_iter().enumerate() {
+            query.set_ally_fleet_force(i, force);
+        }
+    }
+
+    if let Some(s) = get(search, KEY_ALLY_FLEET_TIREDS) {
+        for i in split::<usize>(s).context("invalid ally tired fleets")? {
+            ensure!(
+                (1..=ALLY_FLEET_COUNT).contains(&i),
+                "ally tired fleet is out of range: {i}"
+            );
+            query.set_ally_fleet_is_tired(i - 1, true);
+        }
+    }
+
+    if let Some(s) = get(search, KEY_ALLY_FORMATION) {
+        query.set_ally_formation(s.parse().context("invalid ally formation")?);
+    }
+
+    if let Some(s) = get(search, KEY_ALLY_ABILITIES) {
+        query.set_ally_abilities(split(s).context("invalid ally abilities")?);
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_FLEET_FORCES) {
+        let forces = split_forces(s, ENEMY_FLEET_COUNT).context("invalid enemy fleet forces")?;
+        for (i, force) in forces.into_iter().enumerate() {
+            query.set_enemy_fleet_force(i, force);
+        }
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_FLEET_TIREDS) {
+        for i in split::<usize>(s).context("invalid enemy tired fleets")? {
+            ensure!(
+                (1..=ENEMY_FLEET_COUNT).contains(&i),
+                "enemy tired fleet is out of range: {i}"
+            );
+            query.set_enemy_fleet_is_tired(i - 1, true);
+        }
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_GUARD_FORCE) {
+        query.set_enemy_guard_force(s.parse().context("invalid enemy guard force")?);
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_GUARD_IS_TIRED) {
+        query.set_enemy_guard_is_tired(s != "0");
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_FORMATION) {
+        query.set_enemy_formation(s.parse().context("invalid enemy formation")?);
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_ABILITIES) {
+        query.set_enemy_abilities(split(s).context("invalid enemy abilities")?);
+    }
+
+    if let Some(s) = get(search, KEY_ENEMY_HAS_YANG) {
+        query.set_enemy_has_yang(s != "0");
+    }
+
+    Ok(query)
+}
+
+fn get<'a>(search: &'a UrlSearch, key: &str) -> Option<&'a str> {
+    search
+        .get(key)
+        .and_then(|values| values.first())
+        .map(String::as_str)
+}
+
+fn join<T: ToString>(xs: impl IntoIterator<Item = T>) -> String {
+    xs.into_iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn join_forces(forces: impl IntoIterator<Item = FleetForce>) -> String {
+    let mut forces: Vec<_> = forces.into_iter().collect();
+    while forces.last().is_some_and(|force| force.is_zero()) {
+        forces.pop();
+    }
+
+    join(forces)
+}
+
+fn split<T>(s: &str) -> anyhow::Result<Vec<T>>
+where
+    T: std::str::FromStr,
+    T::Err: Into<anyhow::Error>,
+{
+    if s.is_empty() {
+        return Ok(vec![]);
+    }
+
+    s.split(',')
+        .map(|x| x.parse::<T>().map_err(Into::into))
+        .collect()
+}
+
+/// カンマ区切りの兵力を最大 count 個読み取る。足りない分は 0 で埋める。
+fn split_forces(s: &str, count: usize) -> anyhow::Result<Vec<FleetForce>> {
+    let mut forces = split::<FleetForce>(s)?;
+    ensure!(
+        forces.len() <= count,
+        "too many fleets: {} (max {count})",
+        forces.len()
+    );
+    forces.resize(count, FleetForce::zero());
+
+    Ok(forces)
+}
@@ -0,0 +1,101 @@
+//! ブラウザの localStorage への保存 (名前付きプリセットと前回のセッション)。
+//!
+//! 保存するデータは `{ "version": 1, "data": ... }` の形で、`version` が `STORAGE_VERSION` と
+//! 異なるものは読み込まない。各 `Query` は自身のスキーマバージョンを含むので
+//! (`battle::SCHEMA_VERSION`)、`Query` の形が変わっても古いものを読み込める。
+
+use anyhow::{anyhow, ensure};
+use seed::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::battle::*;
+
+const KEY_PRESETS: &str = "logh-battle.presets";
+const KEY_SESSION: &str = "logh-battle.session";
+
+/// 保存形式のバージョン。保存するデータ全体の形を変えたら上げる。
+const STORAGE_VERSION: u32 = 1;
+
+/// 名前付きの query。
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct NamedQuery {
+    pub(super) name: String,
+    pub(super) query: Query,
+}
+
+/// 前回のセッション (シナリオの一覧とアクティブなシナリオ)。
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct Session {
+    pub(super) scenarios: Vec<NamedQuery>,
+    pub(super) active: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Stored<T> {
+    version: u32,
+    data: T,
+}
+
+/// データ本体を解釈する前にバージョンだけを読むためのもの。
+#[derive(Deserialize)]
+struct StoredVersion {
+    version: u32,
+}
+
+/// 保存したプリセットを読み込む。保存されていなければ空を返す。
+pub(super) fn load_presets() -> anyhow::Result<Vec<NamedQuery>> {
+    Ok(load(KEY_PRESETS)?.unwrap_or_default())
+}
+
+pub(super) fn save_presets(presets: &[NamedQuery]) -> anyhow::Result<()> {
+    save(KEY_PRESETS, presets)
+}
+
+/// 前回のセッションを読み込む。保存されていなければ `None` を返す。
+pub(super) fn load_session() -> anyhow::Result<Option<Session>> {
+    let session: Option<Session> = load(KEY_SESSION)?;
+
+    if let Some(session) = &session {
+        ensure!(
+            session.active < session.scenarios.len(),
+            "active scenario is out of range: {}",
+            session.active
+        );
+    }
+
+    Ok(session)
+}
+
+pub(super) fn save_session(session: &Session) -> anyhow::Result<()> {
+    save(KEY_SESSION, session)
+}
+
+fn load<T: DeserializeOwned>(key: &str) -> anyhow::Result<Option<T>> {
+    let stored: StoredVersion = match LocalStorage::get(key) {
+        Ok(stored) => stored,
+        Err(web_storage::WebStorageError::KeyNotFoundError) => return Ok(None),
+        Err(e) => return Err(storage_error(key, e)),
+    };
+    ensure!(
+        stored.version == STORAGE_VERSION,
+        "unsupported storage version for {key}: {}",
+        stored.version
+    );
+
+    let stored: Stored<T> = LocalStorage::get(key).map_err(|e| storage_error(key, e))?;
+
+    Ok(Some(stored.data))
+}
+
+fn save<T: Serialize + ?Sized>(key: &str, data: &T) -> anyhow::Result<()> {
+    let stored = Stored {
+        version: STORAGE_VERSION,
+        data,
+    };
+
+    LocalStorage::insert(key, &stored).map_err(|e| storage_error(key, e))
+}
+
+fn storage_error(key: &str, e: web_storage::WebStorageError) -> anyhow::Error {
+    anyhow!("localStorage error for {key}: {e:?}")
+}
@@ -0,0 +1,185 @@
+//! 実機で記録した戦闘結果 (`tests/golden/battles.txt`) と `battle_simulate()` の結果の比較。
+//!
+//! データの書式は battles.txt の先頭のコメントを参照。
+
+use std::collections::HashSet;
+
+use anyhow::{bail, ensure, Context as _};
+
+use logh_battle::battle::*;
+
+const CORPUS: &str = include_str!("golden/battles.txt");
+
+#[derive(Debug)]
+struct Record {
+    name: String,
+    /// ブロックの先頭の行番号 (1 始まり)。
+    line: usize,
+    input: Query,
+    /// 兵力は戦闘後の兵力、フォーメーションは修正後のフォーメーション。
+    expect: Query,
+}
+
+fn parse_corpus(corpus: &str) -> anyhow::Result<Vec<Record>> {
+    let mut records = Vec::<Record>::new();
+    let mut block = Vec::<(usize, &str)>::new();
+
+    let lines = corpus
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.starts_with('#'))
+        .chain(std::iter::once((0, "")));
+
+    for (line_no, line) in lines {
+        if line.is_empty() {
+            if !block.is_empty() {
+                let line_first = block[0].0;
+                let record = parse_record(&block)
+                    .with_context(|| format!("invalid record at line {line_first}"))?;
+                records.push(record);
+                block.clear();
+            }
+        } else {
+            block.push((line_no, line));
+        }
+    }
+
+    Ok(records)
+}
+
+fn parse_record(block: &[(usize, &str)]) -> anyhow::Result<Record> {
+    let mut name = None;
+    let mut input = None;
+    let mut expect = None;
+
+    for &(line_no, line) in block {
+        let (key, value) = line
+            .split_once(':')
+            .with_context(|| format!("line {line_no}: should be `key: value`"))?;
+        let value = value.trim();
+
+        let slot_dup = match key {
+            "name" => name.replace(value.to_owned()).is_some(),
+            "input" => input
+                .replace(
+                    value
+                        .parse::<Query>()
+                        .with_context(|| format!("line {line_no}"))?,
+                )
+                .is_some(),
+            "expect" => expect
+                .replace(
+                    value
+                        .parse::<Query>()
+                        .with_context(|| format!("line {line_no}"))?,
+                )
+                .is_some(),
+            "note" => false,
+            _ => bail!("line {line_no}: unknown key: {key}"),
+        };
+        ensure!(!slot_dup, "line {line_no}: duplicate key: {key}");
+    }
+
+    Ok(Record {
+        name: name.context("name is missing")?,
+        line: block[0].0,
+        input: input.context("input is missing")?,
+        expect: expect.context("expect is missing")?,
+    })
+}
+
+/// 記録と戦闘結果の食い違いを項目ごとに返す。
+fn compare(expect: &Query, report: &Report) -> Vec<String> {
+    let mut mismatches = Vec::<String>::new();
+
+    let mut check = |field: String, expected: String, actual: String| {
+        if expected != actual {
+            mismatches.push(format!("{field}: expected {expected}, got {actual}"));
+        }
+    };
+
+    check(
+        "ally formation".to_owned(),
+        expect.ally_formation().to_string(),
+        report.ally_formation().to_string(),
+    );
+    for i in 0..ALLY_FLEET_COUNT {
+        check(
+            format!("ally fleet {}", i + 1),
+            expect.ally_fleet_force(i).to_string(),
+            report.ally_fleet_force(i).to_string(),
+        );
+    }
+
+    check(
+        "enemy formation".to_owned(),
+        expect.enemy_formation().to_string(),
+        report.enemy_formation().to_string(),
+    );
+    check(
+        "enemy guard".to_owned(),
+        expect.enemy_guard_force().to_string(),
+        report.enemy_guard_force().to_string(),
+    );
+    for i in 0..ENEMY_FLEET_COUNT {
+        check(
+            format!("enemy fleet {}", i + 1),
+            expect.enemy_fleet_force(i).to_string(),
+            report.enemy_fleet_force(i).to_string(),
+        );
+    }
+
+    mismatches
+}
+
+#[test]
+fn golden_records_are_well_formed() {
+    let records = parse_corpus(CORPUS).unwrap();
+
+    let mut names = HashSet::<&str>::new();
+    for record in &records {
+        assert!(
+            names.insert(&record.name),
+            "duplicate record name at line {}: {}",
+            record.line,
+            record.name
+        );
+    }
+}
+
+// 実機の記録がまだないので、記録を追加するまでは無視する (`cargo test -- --ignored` で実行できる)。
+#[test]
+#[ignore = "no in-game recordings in tests/golden/battles.txt yet"]
+fn golden_battles_match() {
+    let rules = RuleSet::default();
+    let records = parse_corpus(CORPUS).unwrap();
+    assert!(!records.is_empty(), "corpus has no records");
+
+    let failures: Vec<String> = records
+        .iter()
+        .filter_map(|record| {
+            let mismatches = match battle_simulate(&record.input, &rules) {
+                Some(report) => compare(&record.expect, &report),
+                None => vec!["input is invalid".to_owned()],
+            };
+
+            (!mismatches.is_empty()).then(|| {
+                format!(
+                    "{} (line {}):\n    {}",
+                    record.name,
+                    record.line,
+                    mismatches.join("\n    ")
+                )
+            })
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} record(s) mismatched:\n{}",
+        failures.len(),
+        records.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,483 @@
+//! ランダムに生成した `Query` に対する戦闘計算の不変条件のテスト。
+//!
+//! 反例が見つかったら、条件を破ったままの範囲で `Query` を単純化 (shrink) してから報告する。
+//! 乱数のシードは環境変数 `PROPERTY_SEED` で変えられる。
+
+use logh_battle::battle::*;
+
+/// 1 つの性質あたりに試す入力の数。
+const CASE_COUNT: usize = 2000;
+
+const SEED_DEFAULT: u64 = 0x4c4f_4748_4241_5454;
+
+/// xorshift64* による疑似乱数生成器。
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // 状態が 0 だと 0 しか出ないので避ける。
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// 0 以上 n 未満の値を返す。
+    fn below(&mut self, n: u32) -> u32 {
+        u32::try_from(self.next_u64() % u64::from(n)).unwrap()
+    }
+
+    /// 確率 numer/denom で true を返す。
+    fn chance(&mut self, numer: u32, denom: u32) -> bool {
+        self.below(denom) < numer
+    }
+
+    /// 艦隊の兵力を返す。全滅判定や上限の付近の値を多めに出す。
+    fn fleet_force(&mut self) -> FleetForce {
+        let inner = match self.below(4) {
+            0 => [1, 8, 9, 10, 11, 99, 100][self.below(7) as usize],
+            1 => self.below(16),
+            _ => self.below(101),
+        };
+        FleetForce::new(inner).unwrap()
+    }
+
+    fn formation(&mut self) -> Formation {
+        Formation::new(u8::try_from(self.below(8)).unwrap()).unwrap()
+    }
+
+    fn ability(&mut self) -> Ability {
+        if self.chance(1, 2) {
+            return Ability::YANG;
+        }
+
+        let mut ability = Ability::new(self.below(21), 1 + self.below(4), self.below(6)).unwrap();
+        if self.chance(1, 3) {
+            ability = ability.with_exempt(self.formation());
+        }
+        ability
+    }
+
+    fn query(&mut self) -> Query {
+        let mut query = Query::default();
+
+        let ally_count = self.below(ALLY_FLEET_COUNT as u32 + 1) as usize;
+        for i in 0..ALLY_FLEET_COUNT {
+            let force = if i < ally_count {
+                self.fleet_force()
+            } else {
+                FleetForce::zero()
+            };
+            query.set_ally_fleet_force(i, force);
+            query.set_ally_fleet_is_tired(i, self.chance(1, 4));
+        }
+        query.set_ally_formation(self.formation());
+        query.set_ally_abilities((0..self.below(3)).map(|_| self.ability()).collect());
+
+        let enemy_count = self.below(ENEMY_FLEET_COUNT as u32 + 1) as usize;
+        for i in 0..ENEMY_FLEET_COUNT {
+            let force = if i < enemy_count {
+                self.fleet_force()
+            } else {
+                FleetForce::zero()
+            };
+            query.set_enemy_fleet_force(i, force);
+            query.set_enemy_fleet_is_tired(i, self.chance(1, 4));
+        }
+        let guard_force = if self.chance(1, 3) {
+            FleetForce::zero()
+        } else {
+            self.fleet_force()
+        };
+        query.set_enemy_guard_force(guard_force);
+        query.set_enemy_guard_is_tired(self.chance(1, 4));
+        query.set_enemy_formation(self.formation());
+        query.set_enemy_abilities((0..self.below(3)).map(|_| self.ability()).collect());
+
+        query
+    }
+}
+
+fn seed() -> u64 {
+    std::env::var("PROPERTY_SEED")
+        .ok()
+        .map(|s| {
+            let s = s.trim_start_matches("0x");
+            u64::from_str_radix(s, 16).expect("PROPERTY_SEED should be a hexadecimal number")
+        })
+        .unwrap_or(SEED_DEFAULT)
+}
+
+/// query を 1 段階単純にした候補を返す。
+///
+/// 艦隊は取り除いて後ろの艦隊を詰め、兵力は 0 に近づけ、疲労は解除し、フォーメーションは小さくし、能力は取り除く。
+fn shrink_candidates(query: &Query) -> Vec<Query> {
+    let mut candidates = Vec::<Query>::new();
+    let mut push = |f: &dyn Fn(&mut Query)| {
+        let mut candidate = query.clone();
+        f(&mut candidate);
+        if candidate != *query {
+            candidates.push(candidate);
+        }
+    };
+
+    for i in 0..ALLY_FLEET_COUNT {
+        push(&|q| {
+            for j in i..ALLY_FLEET_COUNT {
+                let (force, is_tired) = if j + 1 < ALLY_FLEET_COUNT {
+                    (q.ally_fleet_force(j + 1), q.ally_fleet_is_tired(j + 1))
+                } else {
+                    (FleetForce::zero(), false)
+                };
+                q.set_ally_fleet_force(j, force);
+                q.set_ally_fleet_is_tired(j, is_tired);
+            }
+        });
+        for force in shrink_force(query.ally_fleet_force(i)) {
+            push(&|q| q.set_ally_fleet_force(i, force));
+        }
+        push(&|q| q.set_ally_fleet_is_tired(i, false));
+    }
+    for formation in shrink_formation(query.ally_formation()) {
+        push(&|q| q.set_ally_formation(formation));
+    }
+    for i in 0..query.ally_abilities().len() {
+        push(&|q| {
+            let mut abilities = q.ally_abilities().to_vec();
+            abilities.remove(i);
+            q.set_ally_abilities(abilities);
+        });
+    }
+
+    for i in 0..ENEMY_FLEET_COUNT {
+        push(&|q| {
+            for j in i..ENEMY_FLEET_COUNT {
+                let (force, is_tired) = if j + 1 < ENEMY_FLEET_COUNT {
+                    (q.enemy_fleet_force(j + 1), q.enemy_fleet_is_tired(j + 1))
+                } else {
+                    (FleetForce::zero(), false)
+                };
+                q.set_enemy_fleet_force(j, force);
+                q.set_enemy_fleet_is_tired(j, is_tired);
+            }
+        });
+        for force in shrink_force(query.enemy_fleet_force(i)) {
+            push(&|q| q.set_enemy_fleet_force(i, force));
+        }
+        push(&|q| q.set_enemy_fleet_is_tired(i, false));
+    }
+    for force in shrink_force(query.enemy_guard_force()) {
+        push(&|q| q.set_enemy_guard_force(force));
+    }
+    push(&|q| q.set_enemy_guard_is_tired(false));
+    for formation in shrink_formation(query.enemy_formation()) {
+        push(&|q| q.set_enemy_formation(formation));
+    }
+    for i in 0..query.enemy_abilities().len() {
+        push(&|q| {
+            let mut abilities = q.enemy_abilities().to_vec();
+            abilities.remove(i);
+            q.set_enemy_abilities(abilities);
+        });
+    }
+
+    candidates
+}
+
+fn shrink_force(force: FleetForce) -> Vec<FleetForce> {
+    let x = force.inner();
+    let mut xs = vec![0, x / 2, x.saturating_sub(1)];
+    xs.dedup();
+
+    xs.into_iter()
+        .filter(|&y| y < x)
+        .map(|y| FleetForce::new(y).unwrap())
+        .collect()
+}
+
+fn shrink_formation(formation: Formation) -> Vec<Formation> {
+    let x = formation.index();
+    let mut xs = vec![0, x.saturating_sub(1)];
+    xs.dedup();
+
+    xs.into_iter()
+        .filter(|&y| y < x)
+        .map(|y| Formation::new(u8::try_from(y).unwrap()).unwrap())
+        .collect()
+}
+
+/// 条件を破る query を、条件を破ったまま単純化できなくなるまで単純化する。
+fn shrink<P>(query: Query, message: String, property: &P) -> (Query, String)
+where
+    P: Fn(&Query) -> Result<(), String>,
+{
+    let mut query = query;
+    let mut message = message;
+
+    'outer: loop {
+        for candidate in shrink_candidates(&query) {
+            if let Err(e) = property(&candidate) {
+                query = candidate;
+                message = e;
+                continue 'outer;
+            }
+        }
+        break;
+    }
+
+    (query, message)
+}
+
+/// ランダムな入力に対して property を検査し、反例があれば単純化して panic する。
+fn check<P>(name: &str, property: P)
+where
+    P: Fn(&Query) -> Result<(), String>,
+{
+    let seed = seed();
+    let mut rng = Rng::new(seed);
+
+    for case in 0..CASE_COUNT {
+        let query = rng.query();
+        if let Err(e) = property(&query) {
+            let (minimal, message) = shrink(query.clone(), e, &property);
+            panic!(
+                "property `{name}` failed (PROPERTY_SEED={seed:x}, case {case}): {message}\n\
+                 minimal query:  {minimal}\n\
+                 original query: {query}"
+            );
+        }
+    }
+}
+
+/// 全艦隊について (項目名, 戦闘前の兵力, 戦闘後の兵力, 全滅判定の閾値, 1 艦隊あたりのダメージ) を返す。
+fn fleet_results(
+    query: &Query,
+    report: &Report,
+    rules: &RuleSet,
+) -> Vec<(String, u32, u32, u32, u32)> {
+    let ally = (0..ALLY_FLEET_COUNT).map(|i| {
+        (
+            format!("ally fleet {}", i + 1),
+            query.ally_fleet_force(i).inner(),
+            report.ally_fleet_force(i).inner(),
+            rules.annihilation_fleet(),
+            report.ally_damage_per_fleet(),
+        )
+    });
+    let enemy = (0..ENEMY_FLEET_COUNT).map(|i| {
+        (
+            format!("enemy fleet {}", i + 1),
+            query.enemy_fleet_force(i).inner(),
+            report.enemy_fleet_force(i).inner(),
+            rules.annihilation_fleet(),
+            report.enemy_damage_per_fleet(),
+        )
+    });
+    let guard = std::iter::once((
+        "enemy guard".to_owned(),
+        query.enemy_guard_force().inner(),
+        report.enemy_guard_force().inner(),
+        rules.annihilation_guard(),
+        report.enemy_damage_per_fleet(),
+    ));
+
+    ally.chain(enemy).chain(guard).collect()
+}
+
+/// 結果のうち、攻撃力が反映される部分 (ダメージと修正後フォーメーション) が等しいかを調べる。
+fn compare_outcomes(report: &Report, other: &Report) -> Result<(), String> {
+    let outcome = |r: &Report| {
+        (
+            r.ally_damage_per_fleet(),
+            r.enemy_damage_per_fleet(),
+            r.ally_formation(),
+            r.enemy_formation(),
+        )
+    };
+
+    if outcome(report) == outcome(other) {
+        Ok(())
+    } else {
+        Err(format!(
+            "(ally damage, enemy damage, ally formation, enemy formation) changed: {:?} -> {:?}",
+            outcome(report),
+            outcome(other)
+        ))
+    }
+}
+
+#[test]
+fn simulate_returns_none_exactly_when_invalid() {
+    let rules = RuleSet::default();
+
+    check("simulate_returns_none_exactly_when_invalid", |query| {
+        let is_valid = query.is_valid();
+        let is_some = battle_simulate(query, &rules).is_some();
+        if is_valid == is_some {
+            Ok(())
+        } else {
+            Err(format!(
+                "is_valid() is {is_valid} but result is_some() is {is_some}"
+            ))
+        }
+    });
+}
+
+#[test]
+fn forces_never_increase() {
+    let rules = RuleSet::default();
+
+    check("forces_never_increase", |query| {
+        let Some(report) = battle_simulate(query, &rules) else {
+            return Ok(());
+        };
+
+        for (field, before, after, _, _) in fleet_results(query, &report, &rules) {
+            if after > before {
+                return Err(format!("{field}: {before} -> {after}"));
+            }
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn damage_per_fleet_is_capped() {
+    let rules = RuleSet::default();
+
+    check("damage_per_fleet_is_capped", |query| {
+        let Some(report) = battle_simulate(query, &rules) else {
+            return Ok(());
+        };
+
+        for (side, damage) in [
+            ("ally", report.ally_damage_per_fleet()),
+            ("enemy", report.enemy_damage_per_fleet()),
+        ] {
+            if damage > 100 {
+                return Err(format!("{side} damage per fleet is {damage}"));
+            }
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn fleets_at_or_below_threshold_are_annihilated() {
+    let rules = RuleSet::default();
+
+    check("fleets_at_or_below_threshold_are_annihilated", |query| {
+        let Some(report) = battle_simulate(query, &rules) else {
+            return Ok(());
+        };
+
+        for (field, before, after, annihilation, damage) in fleet_results(query, &report, &rules) {
+            let remaining = before.saturating_sub(damage);
+            let expected = if remaining <= annihilation {
+                0
+            } else {
+                remaining
+            };
+            if after != expected {
+                return Err(format!(
+                    "{field}: {before} - {damage} (annihilation {annihilation}) should be {expected}, got {after}"
+                ));
+            }
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn tired_fleets_do_not_attack() {
+    let rules = RuleSet::default();
+
+    check("tired_fleets_do_not_attack", |query| {
+        let Some(report) = battle_simulate(query, &rules) else {
+            return Ok(());
+        };
+
+        // 疲労中の艦隊の兵力を (艦隊数が変わらないよう) 0 以外の値に変えても、攻撃力は変わらない。
+        for force in [FleetForce::new(1).unwrap(), FleetForce::MAX] {
+            for i in 0..ALLY_FLEET_COUNT {
+                if query.ally_fleet_is_tired(i) && !query.ally_fleet_force(i).is_zero() {
+                    let mut other = query.clone();
+                    other.set_ally_fleet_force(i, force);
+                    let other = battle_simulate(&other, &rules).unwrap();
+                    compare_outcomes(&report, &other)
+                        .map_err(|e| format!("tired ally fleet {} set to {force}: {e}", i + 1))?;
+                }
+            }
+
+            for i in 0..ENEMY_FLEET_COUNT {
+                if query.enemy_fleet_is_tired(i) && !query.enemy_fleet_force(i).is_zero() {
+                    let mut other = query.clone();
+                    other.set_enemy_fleet_force(i, force);
+                    let other = battle_simulate(&other, &rules).unwrap();
+                    compare_outcomes(&report, &other)
+                        .map_err(|e| format!("tired enemy fleet {} set to {force}: {e}", i + 1))?;
+                }
+            }
+
+            if query.enemy_guard_is_tired() && !query.enemy_guard_force().is_zero() {
+                let mut other = query.clone();
+                other.set_enemy_guard_force(force);
+                let other = battle_simulate(&other, &rules).unwrap();
+                compare_outcomes(&report, &other)
+                    .map_err(|e| format!("tired enemy guard set to {force}: {e}"))?;
+            }
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn yang_applies_unless_ally_formation_is_5() {
+    let rules = RuleSet::default();
+    let formation_5 = Formation::new(5).unwrap();
+    // ヤンと同じ補正で、無効になるフォーメーションのない能力。
+    let yang_unconditional = Ability::new(
+        Ability::YANG.attack_bonus(),
+        Ability::YANG.defense_divisor(),
+        Ability::YANG.defense_offset(),
+    )
+    .unwrap();
+
+    check("yang_applies_unless_ally_formation_is_5", |query| {
+        if !query.enemy_has_yang() {
+            return Ok(());
+        }
+        let Some(report) = battle_simulate(query, &rules) else {
+            return Ok(());
+        };
+
+        // 敵のヤンを、味方のフォーメーションが 5 なら取り除き、そうでなければ常に有効な能力に置き換える。
+        let mut other = query.clone();
+        let abilities: Vec<_> = query
+            .enemy_abilities()
+            .iter()
+            .filter_map(|&ability| {
+                if ability != Ability::YANG {
+                    Some(ability)
+                } else if query.ally_formation() == formation_5 {
+                    None
+                } else {
+                    Some(yang_unconditional)
+                }
+            })
+            .collect();
+        other.set_enemy_abilities(abilities);
+        let other = battle_simulate(&other, &rules).unwrap();
+
+        compare_outcomes(&report, &other).map_err(|e| {
+            format!(
+                "enemy yang with ally formation {}: {e}",
+                query.ally_formation()
+            )
+        })
+    });
+}